@@ -82,13 +82,14 @@ printf '{{ data.from }}' > "$TMP_DIR/tpl2.tpl"
 out=$("$BIN" --source - --format json -t "$TMP_DIR/tpl2.tpl" <<< '{"from":"pipe"}')
 assert_exact "$out" "pipe" "stdin mode with explicit format"
 
-if output=$("$BIN" --source - -t "$TMP_DIR/tpl2.tpl" <<< '{"from":"pipe2"}' 2>&1); then
-  echo "[fail] stdin requires explicit format"
-  echo "  expected command to fail"
-  fail_count=$((fail_count + 1))
-else
-  assert_contains "$output" "When reading from stdin, --format must be specified (json, yaml, or toml)." "stdin requires explicit format"
-fi
+out=$("$BIN" --source - -t "$TMP_DIR/tpl2.tpl" <<< '{"from":"pipe2"}')
+assert_exact "$out" "pipe2" "stdin format detected from content"
+
+out=$("$BIN" --source - -t "$TMP_DIR/tpl2.tpl" <<< 'from: yaml-pipe')
+assert_exact "$out" "yaml-pipe" "stdin YAML detected from content"
+
+run_expected_fail "Could not detect the format of 'stdin'" "stdin with undetectable content" \
+  bash -c "echo 'plain text' | \"$BIN\" --source - -t \"$TMP_DIR/tpl2.tpl\""
 
 printf '{"name":"x"}' > "$TMP_DIR/bad.bin"
 run_expected_fail "Unsupported input format for file" "unknown extension requires explicit --format" \
@@ -1,6 +1,10 @@
+use crate::engine::DumpFormat;
+use crate::merge::{ArrayMerge, PathStrategy};
+use crate::source::SourceSpec;
 use crate::utils::InputFormat;
-use clap::{CommandFactory, Parser};
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser};
 use clap_complete::Shell;
+use glob::Pattern;
 use std::ffi::OsString;
 
 #[derive(Parser)]
@@ -20,7 +24,23 @@ pub struct Cli {
         help = "List all available template filters and exit"
     )]
     pub list_filters: bool,
-    #[arg(long = "template", short = 't', help = "Path to the template file or directory", required_unless_present_any = ["completions", "list_filters"])]
+
+    #[arg(
+        long = "explain-data",
+        help = "Print every value of the final data with the source, --set option or environment that supplied it, plus the values it overrode, and exit"
+    )]
+    pub explain_data: bool,
+
+    #[arg(
+        long = "dump-context",
+        value_name = "FORMAT",
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "json",
+        help = "Print the context passed to templates (the final data under 'data') as json (default), yaml or toml, and exit"
+    )]
+    pub dump_context: Option<DumpFormat>,
+    #[arg(long = "template", short = 't', help = "Path to the template file or directory", required_unless_present_any = ["completions", "list_filters", "explain_data", "dump_context"])]
     pub template_path: Option<OsString>,
 
     #[arg(
@@ -34,26 +54,221 @@ pub struct Cli {
         long = "source",
         short = 's',
         allow_hyphen_values = true,
-        help = "Path to data source file(s) (JSON, YAML, or TOML), or '-' for stdin. Can be specified multiple times.",
+        value_name = "[NAME=]PATH",
+        value_parser = SourceSpec::parse,
+        help = "Data source: a file (JSON, JSON5, YAML, TOML, CSV, TSV, XML, Markdown, dotenv, INI, properties, or NDJSON), a directory or glob, 'sqlite:FILE', or '-' for stdin. Prefix with NAME= to mount the source under data.NAME instead of merging it at the root, and with '?' to skip it when the file does not exist. Append '?key=value&key=value' options (docs=array|each for YAML streams, sort=[-]FIELD for Markdown collections, query=SQL or queries=FILE for sqlite: sources) and '#SELECTOR' (a JSON Pointer or dotted path) to use part of the data. SQL given with query= cannot contain '&' or '#'; use queries=FILE for such queries. Can be specified multiple times.",
         num_args = 1,
-        required_unless_present_any = ["completions", "list_filters"],
+        required_unless_present_any = ["completions", "list_filters", "inline_data", "inline_yaml", "optional_sources", "lookup_sources"],
+    )]
+    pub json_source: Vec<SourceSpec>,
+
+    #[arg(
+        long = "source-optional",
+        value_name = "[NAME=]PATH",
+        value_parser = parse_optional_source,
+        help = "Like --source, but skipped with a message when the file does not exist (same as --source '?PATH'). Can be specified multiple times."
+    )]
+    pub optional_sources: Vec<SourceSpec>,
+
+    #[arg(
+        long = "source-lookup",
+        value_name = "PATTERN",
+        value_parser = SourceSpec::parse_lookup,
+        help = "Hierarchical optional sources from a pattern such as 'config/{env}/{host}.yaml', with placeholders filled from --set values. Loads config.yaml, config/ENV.yaml and config/ENV/HOST.yaml, each when it exists. Can be specified multiple times."
     )]
-    pub json_source: Vec<String>,
+    pub lookup_sources: Vec<SourceSpec>,
+
+    #[arg(
+        long = "data",
+        value_name = "TEXT",
+        allow_hyphen_values = true,
+        help = "Inline data document, e.g. '{\"name\":\"x\"}'. JSON, TOML and YAML are detected from the content unless --format is given. Merged in command-line order together with --source. Can be specified multiple times."
+    )]
+    pub inline_data: Vec<String>,
+
+    #[arg(
+        long = "data-yaml",
+        value_name = "YAML",
+        allow_hyphen_values = true,
+        help = "Inline YAML data document, merged like --data. Can be specified multiple times."
+    )]
+    pub inline_yaml: Vec<String>,
 
     #[arg(
         long = "format",
         short = 'f',
-        help = "Input format (json, yaml, toml) - auto-detected for files, required for stdin",
+        help = "Input format (json, json5, yaml, toml, csv, tsv, xml, dotenv, ini, properties, ndjson, markdown) - detected from the file extension, or from content for stdin and extension-less files",
         value_enum
     )]
     pub input_format: Option<InputFormat>,
 
+    #[arg(
+        long = "csv-delimiter",
+        value_name = "CHAR",
+        value_parser = parse_delimiter,
+        help = "Field delimiter for CSV/TSV sources (single ASCII character, '\\t' for tab)"
+    )]
+    pub csv_delimiter: Option<u8>,
+
+    #[arg(
+        long = "csv-no-header",
+        help = "Treat the first CSV/TSV row as data and produce arrays instead of objects"
+    )]
+    pub csv_no_header: bool,
+
+    #[arg(
+        long = "csv-infer-types",
+        help = "Convert CSV/TSV fields that look like numbers or booleans into typed values"
+    )]
+    pub csv_infer_types: bool,
+
+    #[arg(
+        long = "xml-array",
+        value_name = "PATH",
+        help = "Dotted XML element path (from the root) that is always converted to an array (can be used multiple times)",
+        num_args = 1
+    )]
+    pub xml_arrays: Vec<String>,
+
+    #[arg(
+        long = "markdown-html",
+        help = "Also render the body of Markdown sources to HTML under 'html'"
+    )]
+    pub markdown_html: bool,
+
+    #[arg(
+        long = "expand-dotted-keys",
+        help = "Expand dotted keys in INI and .properties sources (a.b=1) into nested objects"
+    )]
+    pub expand_dotted_keys: bool,
+
+    #[arg(
+        long = "keep-refs",
+        help = "Leave {\"$ref\": \"file\"} objects and YAML !include tags in the data instead of loading the referenced files"
+    )]
+    pub keep_refs: bool,
+
+    #[arg(
+        long = "merge-arrays",
+        value_name = "STRATEGY",
+        value_enum,
+        default_value_t = ArrayMerge::Replace,
+        help = "How arrays from later sources are combined with earlier ones"
+    )]
+    pub merge_arrays: ArrayMerge,
+
+    #[arg(
+        long = "merge-path",
+        value_name = "PATH=STRATEGY[:KEY]",
+        value_parser = PathStrategy::parse,
+        help = "Array merge strategy for one dotted path, e.g. 'spec.ports=append' or 'middlewares=merge-by-key:name' (can be used multiple times)",
+        num_args = 1
+    )]
+    pub merge_paths: Vec<PathStrategy>,
+
+    #[arg(
+        long = "merge-key",
+        value_name = "KEY",
+        default_value = "name",
+        help = "Default item key used by the merge-by-key strategy"
+    )]
+    pub merge_key: String,
+
+    #[arg(
+        long = "merge-null-deletes",
+        help = "Treat null values in later sources as tombstones that delete the key"
+    )]
+    pub merge_null_deletes: bool,
+
     #[arg(
         long = "env-vars",
         help = "Include environment variables in template data as 'env' object"
     )]
     pub include_env_vars: bool,
 
+    #[arg(
+        long = "env-prefix",
+        value_name = "PREFIX",
+        help = "Only include environment variables starting with PREFIX; the prefix is removed from the keys (implies --env-vars)"
+    )]
+    pub env_prefix: Option<String>,
+
+    #[arg(
+        long = "env-keep-prefix",
+        requires = "env_prefix",
+        help = "Keep the --env-prefix in the variable keys"
+    )]
+    pub env_keep_prefix: bool,
+
+    #[arg(
+        long = "env-separator",
+        value_name = "SEP",
+        value_parser = clap::builder::NonEmptyStringValueParser::new(),
+        help = "Split environment variable names on SEP into nested objects, e.g. '__' (implies --env-vars)"
+    )]
+    pub env_separator: Option<String>,
+
+    #[arg(
+        long = "env-lowercase",
+        help = "Lowercase environment variable keys (implies --env-vars)"
+    )]
+    pub env_lowercase: bool,
+
+    #[arg(
+        long = "env-infer-types",
+        help = "Convert environment values that look like numbers, booleans or null into typed values (implies --env-vars)"
+    )]
+    pub env_infer_types: bool,
+
+    #[arg(
+        long = "env-allow",
+        value_name = "PATTERN",
+        value_parser = Pattern::new,
+        help = "Only include environment variables whose name matches PATTERN, e.g. 'APP_*' (can be used multiple times, implies --env-vars)"
+    )]
+    pub env_allow: Vec<Pattern>,
+
+    #[arg(
+        long = "env-deny",
+        value_name = "PATTERN",
+        value_parser = Pattern::new,
+        help = "Exclude environment variables whose name matches PATTERN, e.g. '*_SECRET' (can be used multiple times, implies --env-vars)"
+    )]
+    pub env_deny: Vec<Pattern>,
+
+    #[arg(
+        long = "patch",
+        value_name = "FILE",
+        help = "Apply an RFC 6902 JSON Patch (array of operations) or RFC 7386 JSON Merge Patch (object) to the merged sources. Can be specified multiple times; patches are applied in order"
+    )]
+    pub patches: Vec<String>,
+
+    #[arg(
+        long = "interpolate",
+        help = "Resolve ${path.to.key}, ${env:NAME} and ${NAME:-fallback} references in data string values"
+    )]
+    pub interpolate: bool,
+
+    #[arg(
+        long = "schema",
+        value_name = "FILE",
+        help = "Validate the merged data against a JSON Schema (draft 2020-12) before rendering"
+    )]
+    pub schema: Option<String>,
+
+    #[arg(
+        long = "schema-defaults",
+        requires = "schema",
+        help = "Fill in missing keys from the schema's 'default' values before validating"
+    )]
+    pub schema_defaults: bool,
+
+    #[arg(
+        long = "batch",
+        help = "Render the template once per record of the NDJSON source. --dest becomes a path pattern such as 'out/{{ data.id }}.txt'; without it outputs are concatenated to stdout"
+    )]
+    pub batch: bool,
+
     #[arg(long = "check", help = "Validate the template without rendering")]
     pub check: bool,
 
@@ -73,11 +288,35 @@ pub struct Cli {
     #[arg(
         long = "set",
         value_name = "KEY=VALUE",
-        help = "Set a template variable (can be used multiple times)",
+        help = "Set a template variable by dotted path, e.g. db.port=5432 or servers[0].host=x. Numbers, booleans and null are typed automatically (can be used multiple times)",
         num_args = 1
     )]
     pub set_vars: Vec<String>,
 
+    #[arg(
+        long = "set-string",
+        value_name = "KEY=VALUE",
+        help = "Like --set, but always stores the value as a string (can be used multiple times)",
+        num_args = 1
+    )]
+    pub set_string_vars: Vec<String>,
+
+    #[arg(
+        long = "set-json",
+        value_name = "KEY=JSON",
+        help = "Like --set, but parses the value as JSON, e.g. --set-json 'limits={\"cpu\":2}' (can be used multiple times)",
+        num_args = 1
+    )]
+    pub set_json_vars: Vec<String>,
+
+    #[arg(
+        long = "set-file",
+        value_name = "KEY=PATH",
+        help = "Like --set, but uses the contents of a file: text as-is, binary files base64-encoded (can be used multiple times)",
+        num_args = 1
+    )]
+    pub set_file_vars: Vec<String>,
+
     #[arg(
         long = "quiet",
         short = 'q',
@@ -85,6 +324,14 @@ pub struct Cli {
     )]
     pub quiet: bool,
 
+    #[arg(
+        long = "verbose",
+        short = 'v',
+        conflicts_with = "quiet",
+        help = "Print additional diagnostics on stderr, such as detected input formats"
+    )]
+    pub verbose: bool,
+
     #[arg(
         long = "diff",
         help = "Show diff of what would change in the destination file instead of writing"
@@ -106,6 +353,69 @@ pub struct Cli {
     pub recursive: bool,
 }
 
+impl Cli {
+    /**
+     * Parse the command line. Inline --data and --data-yaml documents, optional
+     * sources and lookups are added to the sources at their position on the
+     * command line, so all data layers merge in the order they were given.
+     * @author: skitsanos
+     */
+    pub fn parse_args() -> Self {
+        let matches = Self::command().get_matches();
+        let mut cli = Self::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+        cli.order_sources(&matches);
+        cli
+    }
+
+    fn order_sources(&mut self, matches: &ArgMatches) {
+        let indices = |id: &str| matches.indices_of(id).into_iter().flatten();
+        let inline_data = self
+            .inline_data
+            .iter()
+            .map(|text| SourceSpec::inline("--data", text, None));
+        let inline_yaml = self
+            .inline_yaml
+            .iter()
+            .map(|text| SourceSpec::inline("--data-yaml", text, Some(InputFormat::Yaml)));
+
+        let mut sources: Vec<(usize, SourceSpec)> = indices("json_source")
+            .zip(self.json_source.drain(..))
+            .chain(indices("optional_sources").zip(self.optional_sources.drain(..)))
+            .chain(indices("lookup_sources").zip(self.lookup_sources.drain(..)))
+            .chain(indices("inline_data").zip(inline_data))
+            .chain(indices("inline_yaml").zip(inline_yaml))
+            .collect();
+        sources.sort_by_key(|(index, _)| *index);
+        self.json_source = sources.into_iter().map(|(_, source)| source).collect();
+    }
+}
+
+/**
+ * Parse a --source-optional value: a --source that may be missing
+ * @author: skitsanos
+ */
+fn parse_optional_source(value: &str) -> Result<SourceSpec, String> {
+    let mut spec = SourceSpec::parse(value)?;
+    spec.optional = true;
+    Ok(spec)
+}
+
+/**
+ * Parse a --csv-delimiter value into a single byte
+ * @author: skitsanos
+ */
+fn parse_delimiter(value: &str) -> Result<u8, String> {
+    match value {
+        "\\t" | "\t" => Ok(b'\t'),
+        _ => match value.as_bytes() {
+            [byte] if byte.is_ascii() => Ok(*byte),
+            _ => Err(format!(
+                "delimiter must be a single ASCII character, got '{value}'"
+            )),
+        },
+    }
+}
+
 /**
  * Generate shell completions and write to stdout
  * @author: skitsanos
@@ -12,12 +12,30 @@ use crate::filters::serialize::{filter_json_encode, filter_yaml_encode};
 use crate::filters::text::filter_truncate_words;
 use crate::filters::url::{filter_url_decode, filter_url_encode};
 use crate::filters::uuid::filter_uuid;
+use clap::ValueEnum;
 use serde_json::Value as JsonValue;
 use std::fs::File;
 use std::io::{self, Write};
 use std::path::Path;
 use tera::{Context, Kwargs, State, Tera, Value};
 
+/**
+ * Name under which the parsed data is exposed to templates
+ * @author: skitsanos
+ */
+const DATA_KEY: &str = "data";
+
+/**
+ * Serialization formats for --dump-context
+ * @author: skitsanos
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DumpFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
 type LegacyFilter = fn(
     &JsonValue,
     &std::collections::HashMap<String, JsonValue>,
@@ -160,12 +178,52 @@ impl TemplateEngine {
         json_data: &JsonValue,
     ) -> Result<String> {
         let template_name = template_path.as_ref().to_string_lossy();
+        let rendered = self
+            .tera
+            .render(&template_name, &Self::context(json_data))?;
+        Ok(rendered)
+    }
 
+    /**
+     * Render an inline template string (e.g. a destination path pattern) with the provided JSON data
+     * @author: skitsanos
+     */
+    pub fn render_str(&self, input: &str, json_data: &JsonValue) -> Result<String> {
+        let rendered = self
+            .tera
+            .render_str(input, &Self::context(json_data), false)?;
+        Ok(rendered)
+    }
+
+    fn context(json_data: &JsonValue) -> Context {
         let mut context = Context::new();
-        context.insert("data", json_data);
+        context.insert(DATA_KEY, json_data);
+        context
+    }
 
-        let rendered = self.tera.render(&template_name, &context)?;
-        Ok(rendered)
+    /**
+     * Serialize the context that render() passes to templates, including the
+     * `data` wrapper. TOML has no null, so data containing nulls cannot be
+     * dumped as TOML.
+     * @author: skitsanos
+     */
+    pub fn dump_context(json_data: &JsonValue, format: DumpFormat) -> Result<String> {
+        let mut context = serde_json::Map::new();
+        context.insert(DATA_KEY.to_string(), json_data.clone());
+        let context = JsonValue::Object(context);
+
+        let dump_error = |label: &str, e: &dyn std::fmt::Display| {
+            TeraclioError::InvalidInput(format!("Cannot dump the context as {label}: {e}"))
+        };
+        match format {
+            DumpFormat::Json => serde_json::to_string_pretty(&context)
+                .map(|json| json + "\n")
+                .map_err(|e| dump_error("JSON", &e)),
+            DumpFormat::Yaml => serde_yaml::to_string(&context).map_err(|e| dump_error("YAML", &e)),
+            DumpFormat::Toml => toml::to_string(&context).map_err(|e| {
+                dump_error("TOML", &format!("{e} (TOML cannot represent null values)"))
+            }),
+        }
     }
 
     /**
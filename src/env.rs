@@ -0,0 +1,179 @@
+use crate::error::{Result, TeraclioError};
+use crate::formats::{infer_scalar, insert_key};
+use glob::Pattern;
+use serde_json::{Map, Value};
+
+/**
+ * Options controlling which environment variables are imported into `data.env`
+ * and how their names and values are shaped
+ * @author: skitsanos
+ */
+#[derive(Clone, Debug, Default)]
+pub struct EnvOptions {
+    /** Only variables starting with this prefix are imported */
+    pub prefix: Option<String>,
+    /** Keep the prefix in the key instead of removing it */
+    pub keep_prefix: bool,
+    /** Split names on this separator into nested objects (e.g. `__`) */
+    pub separator: Option<String>,
+    /** Lowercase the resulting keys */
+    pub lowercase: bool,
+    /** Convert numbers, booleans and null into typed values */
+    pub infer_types: bool,
+    /** When non-empty, only variables matching one of these patterns are imported */
+    pub allow: Vec<Pattern>,
+    /** Variables matching any of these patterns are never imported */
+    pub deny: Vec<Pattern>,
+}
+
+impl EnvOptions {
+    /**
+     * Whether any filtering or shaping option is set. Without them, the whole
+     * environment is imported as flat strings.
+     * @author: skitsanos
+     */
+    pub fn is_configured(&self) -> bool {
+        self.prefix.is_some()
+            || self.separator.is_some()
+            || self.lowercase
+            || self.infer_types
+            || !self.allow.is_empty()
+            || !self.deny.is_empty()
+    }
+
+    fn is_selected(&self, name: &str) -> bool {
+        if let Some(prefix) = &self.prefix {
+            if !name.starts_with(prefix.as_str()) {
+                return false;
+            }
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|p| p.matches(name)) {
+            return false;
+        }
+        !self.deny.iter().any(|p| p.matches(name))
+    }
+
+    fn key_for(&self, name: &str) -> String {
+        let mut key = name;
+        if let (Some(prefix), false) = (&self.prefix, self.keep_prefix) {
+            // `APP_` and `APP` both turn `APP__DB` into `DB`
+            key = key[prefix.len()..].trim_start_matches('_');
+        }
+        if self.lowercase {
+            key.to_lowercase()
+        } else {
+            key.to_string()
+        }
+    }
+}
+
+/**
+ * Build the `env` object from environment variables. Allow and deny patterns are
+ * matched against the original variable names; deny wins over allow. Variables
+ * whose name becomes empty after removing the prefix are skipped.
+ * @author: skitsanos
+ */
+pub fn env_data<I>(vars: I, options: &EnvOptions) -> Result<Value>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let mut vars: Vec<_> = vars
+        .into_iter()
+        .filter(|(name, _)| options.is_selected(name))
+        .collect();
+    // Sorted so nesting conflicts are reported the same way on every run
+    vars.sort();
+
+    let mut map = Map::new();
+    for (name, value) in vars {
+        let key = options.key_for(&name);
+        if key.is_empty() {
+            continue;
+        }
+        let value = if options.infer_types {
+            infer_scalar(&value)
+        } else {
+            Value::String(value)
+        };
+
+        match &options.separator {
+            Some(separator) => {
+                let path = key.split(separator.as_str()).collect::<Vec<_>>().join(".");
+                insert_key(&mut map, &path, value, true).map_err(|_| {
+                    TeraclioError::InvalidInput(format!(
+                        "Cannot nest environment variable '{name}': a parent key already holds a plain value"
+                    ))
+                })?;
+            }
+            None => {
+                map.insert(key, value);
+            }
+        }
+    }
+
+    Ok(Value::Object(map))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{env_data, EnvOptions};
+    use glob::Pattern;
+    use serde_json::json;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn nests_prefixed_variables() {
+        let options = EnvOptions {
+            prefix: Some("APP_".to_string()),
+            separator: Some("__".to_string()),
+            lowercase: true,
+            infer_types: true,
+            ..EnvOptions::default()
+        };
+        let result = env_data(
+            vars(&[
+                ("APP__DB__HOST", "db.local"),
+                ("APP__DB__PORT", "5432"),
+                ("APP__DEBUG", "true"),
+                ("HOME", "/root"),
+            ]),
+            &options,
+        )
+        .expect("env");
+        assert_eq!(
+            result,
+            json!({"db": {"host": "db.local", "port": 5432}, "debug": true})
+        );
+    }
+
+    #[test]
+    fn applies_allow_and_deny_lists() {
+        let options = EnvOptions {
+            allow: vec![Pattern::new("CI_*").expect("pattern")],
+            deny: vec![Pattern::new("*_TOKEN").expect("pattern")],
+            ..EnvOptions::default()
+        };
+        let result = env_data(
+            vars(&[("CI_JOB", "7"), ("CI_TOKEN", "secret"), ("PATH", "/bin")]),
+            &options,
+        )
+        .expect("env");
+        assert_eq!(result, json!({"CI_JOB": "7"}));
+    }
+
+    #[test]
+    fn reports_nesting_conflicts() {
+        let options = EnvOptions {
+            separator: Some("__".to_string()),
+            ..EnvOptions::default()
+        };
+        let err = env_data(vars(&[("DB", "x"), ("DB__HOST", "y")]), &options).expect_err("error");
+        assert!(err.to_string().contains("'DB__HOST'"), "{err}");
+    }
+}
@@ -0,0 +1,260 @@
+use crate::path::{DataPath, Segment};
+use regex::Regex;
+use serde_json::Value;
+use std::fmt::Write as _;
+
+/**
+ * Longest rendering of a value in the report before it is shortened
+ * @author: skitsanos
+ */
+const MAX_VALUE_WIDTH: usize = 80;
+
+/**
+ * One step of the data layering: a parsed source (already mounted) or a value
+ * injected by --env-vars or a --set option, in the order it was merged
+ * @author: skitsanos
+ */
+pub struct Layer {
+    /** File path, `stdin`, `environment` or the --set option */
+    pub origin: String,
+    /** Raw file text, used to find line numbers */
+    pub contents: Option<String>,
+    /** Number of leading path segments added by the source's mount name */
+    pub mount_depth: usize,
+    pub value: Value,
+}
+
+/**
+ * Describe where every leaf of the final data came from. Objects are followed
+ * down to their leaves; arrays are reported as a whole. Values from earlier
+ * layers that differ from the final value are listed as overridden, and leaves
+ * that no longer exist in the final data are listed at the end. A leaf that no
+ * layer supplied comes from a schema default.
+ * @author: skitsanos
+ */
+pub fn explain(data: &Value, layers: &[Layer]) -> String {
+    let mut leaves = Vec::new();
+    collect_leaves(data, DataPath::default(), &mut leaves);
+
+    let mut report = String::new();
+    let _ = writeln!(report, "Data provenance ({} values):", leaves.len());
+    for (path, value) in &leaves {
+        let contributions: Vec<(&Layer, &Value)> = layers
+            .iter()
+            .filter_map(|layer| path.get(&layer.value).map(|value| (layer, value)))
+            .collect();
+
+        let _ = writeln!(report, "\n{} = {}", describe(path), preview(value));
+        match contributions.split_last() {
+            None => {
+                let _ = writeln!(report, "    from default");
+            }
+            Some(((layer, supplied), earlier)) => {
+                // An array that differs from every layer was merged from several of them
+                let merged = value.is_array() && *supplied != *value;
+                let note = if *supplied == *value {
+                    ""
+                } else if merged {
+                    " (merged)"
+                } else {
+                    " (interpolated)"
+                };
+                let _ = writeln!(report, "    from {}{note}", locate(layer, path));
+                let verb = if merged { "merged with" } else { "overrides" };
+                for (layer, earlier_value) in earlier.iter().rev() {
+                    if *earlier_value != *value {
+                        let _ = writeln!(
+                            report,
+                            "    {verb} {} from {}",
+                            preview(earlier_value),
+                            locate(layer, path)
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    let mut removed = Vec::new();
+    for layer in layers {
+        let mut layer_leaves = Vec::new();
+        collect_leaves(&layer.value, DataPath::default(), &mut layer_leaves);
+        for (path, value) in layer_leaves {
+            if path.get(data).is_none() {
+                removed.push((path, value, layer));
+            }
+        }
+    }
+    if !removed.is_empty() {
+        let _ = writeln!(
+            report,
+            "\nOverridden or removed values not in the final data:"
+        );
+        for (path, value, layer) in removed {
+            let _ = writeln!(report, "\n{} = {}", describe(&path), preview(value));
+            let _ = writeln!(report, "    from {}", locate(layer, &path));
+        }
+    }
+
+    report
+}
+
+/**
+ * The part of `after` that differs from `before`, for describing a step such as
+ * a patch that rewrites the data in place. Unchanged keys are left out; null
+ * means nothing changed.
+ * @author: skitsanos
+ */
+pub fn changes(before: &Value, after: &Value) -> Value {
+    changed(Some(before), after).unwrap_or(Value::Null)
+}
+
+fn changed(before: Option<&Value>, after: &Value) -> Option<Value> {
+    match (before, after) {
+        (Some(Value::Object(before)), Value::Object(after)) if !after.is_empty() => {
+            let map: serde_json::Map<String, Value> = after
+                .iter()
+                .filter_map(|(key, value)| {
+                    changed(before.get(key), value).map(|value| (key.clone(), value))
+                })
+                .collect();
+            (!map.is_empty()).then_some(Value::Object(map))
+        }
+        (Some(before), after) if before == after => None,
+        (_, after) => Some(after.clone()),
+    }
+}
+
+fn collect_leaves<'a>(value: &'a Value, path: DataPath, leaves: &mut Vec<(DataPath, &'a Value)>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, child) in map {
+                collect_leaves(child, path.child(Segment::Key(key.clone())), leaves);
+            }
+        }
+        _ => leaves.push((path, value)),
+    }
+}
+
+/**
+ * Name a layer, with the line of the value in its file when it can be found.
+ * Lines are found by searching for each key in turn, so they are best-effort
+ * for formats where a key is written as `key:`, `"key":`, `key =` or `[key]`.
+ * @author: skitsanos
+ */
+fn locate(layer: &Layer, path: &DataPath) -> String {
+    let keys: Vec<&str> = path
+        .segments()
+        .iter()
+        .skip(layer.mount_depth)
+        .filter_map(|segment| match segment {
+            Segment::Key(key) => Some(key.as_str()),
+            Segment::Index(_) => None,
+        })
+        .collect();
+
+    match layer
+        .contents
+        .as_deref()
+        .and_then(|contents| find_line(contents, &keys))
+    {
+        Some(line) => format!("{}:{line}", layer.origin),
+        None => layer.origin.clone(),
+    }
+}
+
+fn find_line(contents: &str, keys: &[&str]) -> Option<usize> {
+    let lines: Vec<&str> = contents.lines().collect();
+    let mut from = 0;
+    let mut found = None;
+    for key in keys {
+        let key = regex::escape(key);
+        let pattern = Regex::new(&format!(
+            r#"^\s*(?:-\s+)?["']?{key}["']?\s*[:=]|"{key}"\s*:|^\s*\[+\s*(?:[^\]]*\.)?["']?{key}["']?\s*\]"#
+        ))
+        .ok()?;
+        let offset = lines
+            .get(from..)?
+            .iter()
+            .position(|line| pattern.is_match(line))?;
+        found = Some(from + offset + 1);
+        from += offset + 1;
+    }
+    found
+}
+
+fn describe(path: &DataPath) -> String {
+    let path = path.to_string();
+    if path.is_empty() {
+        "(root)".to_string()
+    } else {
+        path
+    }
+}
+
+fn preview(value: &Value) -> String {
+    let text = value.to_string();
+    if text.chars().count() <= MAX_VALUE_WIDTH {
+        return text;
+    }
+    let short: String = text.chars().take(MAX_VALUE_WIDTH - 3).collect();
+    format!("{short}...")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{explain, find_line, Layer};
+    use serde_json::json;
+
+    #[test]
+    fn reports_origins_overrides_and_removed_values() {
+        let layers = [
+            Layer {
+                origin: "base.yaml".to_string(),
+                contents: Some("db:\n  host: localhost\n  debug: true\n".to_string()),
+                mount_depth: 0,
+                value: json!({"db": {"host": "localhost", "debug": true}}),
+            },
+            Layer {
+                origin: "--set db.host=prod".to_string(),
+                contents: None,
+                mount_depth: 0,
+                value: json!({"db": {"host": "prod"}}),
+            },
+        ];
+        let data = json!({"db": {"host": "prod"}, "name": "app"});
+
+        let report = explain(&data, &layers);
+        assert!(
+            report.contains(
+                "db.host = \"prod\"\n    from --set db.host=prod\n    overrides \"localhost\" from base.yaml:2\n"
+            ),
+            "{report}"
+        );
+        assert!(
+            report.contains("name = \"app\"\n    from default\n"),
+            "{report}"
+        );
+        assert!(
+            report.contains("db.debug = true\n    from base.yaml:3\n"),
+            "{report}"
+        );
+    }
+
+    #[test]
+    fn finds_lines_in_json_toml_and_yaml() {
+        assert_eq!(
+            find_line("{\n  \"db\": {\n    \"port\": 1\n  }\n}", &["db", "port"]),
+            Some(3)
+        );
+        assert_eq!(
+            find_line("name = \"x\"\n\n[db]\nport = 1\n", &["db", "port"]),
+            Some(4)
+        );
+        assert_eq!(
+            find_line("port: 1\ndb:\n  port: 2\n", &["db", "port"]),
+            Some(3)
+        );
+        assert_eq!(find_line("db:\n  host: x\n", &["db", "port"]), None);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::error::{Result, TeraclioError};
+use crate::formats::infer_scalar;
+use serde_json::{Map, Value};
+
+/**
+ * Options controlling how CSV/TSV data sources are parsed
+ * @author: skitsanos
+ */
+#[derive(Clone, Debug)]
+pub struct CsvOptions {
+    pub delimiter: Option<u8>,
+    pub has_headers: bool,
+    pub infer_types: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: None,
+            has_headers: true,
+            infer_types: false,
+        }
+    }
+}
+
+/**
+ * Parse delimited text into a JSON array. With a header row every record becomes
+ * an object keyed by column name, otherwise every record becomes an array of fields.
+ * @author: skitsanos
+ *
+ * # Arguments
+ *
+ * * `contents` - Raw delimited text
+ * * `default_delimiter` - Delimiter used when the options don't override it
+ * * `options` - Header, delimiter and type inference settings
+ */
+pub fn parse_delimited(
+    contents: &str,
+    default_delimiter: u8,
+    options: &CsvOptions,
+) -> Result<Value> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .delimiter(options.delimiter.unwrap_or(default_delimiter))
+        .has_headers(options.has_headers)
+        .from_reader(contents.as_bytes());
+
+    let headers: Option<Vec<String>> = if options.has_headers {
+        let headers = reader.headers().map_err(csv_error)?;
+        Some(headers.iter().map(str::to_string).collect())
+    } else {
+        None
+    };
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(csv_error)?;
+        let fields = record.iter().map(|field| {
+            // Only numbers and booleans are inferred; "null" stays text
+            match options.infer_types.then(|| infer_scalar(field)) {
+                Some(Value::Null) | None => Value::String(field.to_string()),
+                Some(value) => value,
+            }
+        });
+
+        let row = match &headers {
+            Some(headers) => {
+                Value::Object(headers.iter().cloned().zip(fields).collect::<Map<_, _>>())
+            }
+            None => Value::Array(fields.collect()),
+        };
+        rows.push(row);
+    }
+
+    Ok(Value::Array(rows))
+}
+
+fn csv_error(err: ::csv::Error) -> TeraclioError {
+    TeraclioError::InvalidInput(format!("CSV parsing error: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_delimited, CsvOptions};
+    use serde_json::json;
+
+    #[test]
+    fn header_row_produces_objects() {
+        let result =
+            parse_delimited("name,qty\napple,3\n", b',', &CsvOptions::default()).expect("parse");
+        assert_eq!(result, json!([{"name": "apple", "qty": "3"}]));
+    }
+
+    #[test]
+    fn headerless_mode_produces_arrays_with_inferred_types() {
+        let options = CsvOptions {
+            delimiter: Some(b';'),
+            has_headers: false,
+            infer_types: true,
+        };
+        let result = parse_delimited("a;1;true;007;2.5\n", b',', &options).expect("parse");
+        assert_eq!(result, json!([["a", 1, true, "007", 2.5]]));
+    }
+
+    #[test]
+    fn inferred_types_keep_null_as_text() {
+        let options = CsvOptions {
+            infer_types: true,
+            ..CsvOptions::default()
+        };
+        let result = parse_delimited("a,b\nnull,1\n", b',', &options).expect("parse");
+        assert_eq!(result, json!([{"a": "null", "b": 1}]));
+    }
+
+    #[test]
+    fn reports_ragged_rows() {
+        let err = parse_delimited("a,b\n1,2,3\n", b',', &CsvOptions::default()).expect_err("error");
+        assert!(err.to_string().contains("CSV parsing error"), "{err}");
+    }
+}
@@ -0,0 +1,40 @@
+use crate::error::{Result, TeraclioError};
+use crate::formats::insert_key;
+use serde_json::{Map, Value};
+
+/**
+ * Parse a dotenv file into a flat object of strings. Quoting, escapes and
+ * `${VAR}` references follow the dotenvy crate: references resolve against
+ * the process environment first and then against keys defined earlier in the file.
+ * @author: skitsanos
+ */
+pub fn parse_dotenv(contents: &str) -> Result<Value> {
+    let mut map = Map::new();
+    for item in dotenvy::from_read_iter(contents.as_bytes()) {
+        let (key, value) =
+            item.map_err(|e| TeraclioError::InvalidInput(format!("dotenv parsing error: {e}")))?;
+        insert_key(&mut map, &key, Value::String(value), false)?;
+    }
+    Ok(Value::Object(map))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_dotenv;
+
+    #[test]
+    fn handles_quotes_comments_and_references() {
+        // Names are unusual on purpose: references check the process environment first
+        let contents = "# comment\nTCL_TEST_HOST=localhost\nexport TCL_TEST_PORT='8080'\n\
+                        TCL_TEST_URL=\"http://${TCL_TEST_HOST}:${TCL_TEST_PORT}/\\n\"\n";
+        let result = parse_dotenv(contents).expect("parse");
+        assert_eq!(result["TCL_TEST_PORT"], "8080");
+        assert_eq!(result["TCL_TEST_URL"], "http://localhost:8080/\n");
+    }
+
+    #[test]
+    fn reports_invalid_lines() {
+        let err = parse_dotenv("NAME='unterminated\n").expect_err("error");
+        assert!(err.to_string().contains("dotenv parsing error"), "{err}");
+    }
+}
@@ -0,0 +1,65 @@
+use crate::error::{Result, TeraclioError};
+use crate::formats::insert_key;
+use ini::Ini;
+use serde_json::{Map, Value};
+
+/**
+ * Parse an INI file. Keys outside any section stay at the root and every
+ * `[section]` becomes a nested object. With `expand_dotted` both section names
+ * and keys containing '.' are expanded into deeper objects.
+ * @author: skitsanos
+ */
+pub fn parse_ini(contents: &str, expand_dotted: bool) -> Result<Value> {
+    let ini = Ini::load_from_str(contents)
+        .map_err(|e| TeraclioError::InvalidInput(format!("INI parsing error: {e}")))?;
+
+    let mut root = Map::new();
+    for (section, properties) in ini.iter() {
+        let mut section_map = Map::new();
+        for (key, value) in properties.iter() {
+            insert_key(
+                &mut section_map,
+                key,
+                Value::String(value.to_string()),
+                expand_dotted,
+            )?;
+        }
+
+        match section {
+            None => {
+                for (key, value) in section_map {
+                    root.insert(key, value);
+                }
+            }
+            Some(name) => insert_key(&mut root, name, Value::Object(section_map), expand_dotted)?,
+        }
+    }
+
+    Ok(Value::Object(root))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_ini;
+    use serde_json::json;
+
+    #[test]
+    fn sections_become_nested_objects() {
+        let contents = "name = app\n\n[database]\nhost = \"db.local\"\nport = 5432\n";
+        let result = parse_ini(contents, false).expect("parse");
+        assert_eq!(
+            result,
+            json!({"name": "app", "database": {"host": "db.local", "port": "5432"}})
+        );
+    }
+
+    #[test]
+    fn expands_dotted_section_names_and_keys() {
+        let contents = "[server.http]\ntls.enabled = true\n";
+        let result = parse_ini(contents, true).expect("parse");
+        assert_eq!(
+            result,
+            json!({"server": {"http": {"tls": {"enabled": "true"}}}})
+        );
+    }
+}
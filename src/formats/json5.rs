@@ -0,0 +1,46 @@
+use crate::error::{Result, TeraclioError};
+use serde_json::Value;
+
+/**
+ * Parse relaxed JSON (JSON5, which also covers JSONC): comments, trailing commas,
+ * unquoted keys and single-quoted strings are accepted.
+ * @author: skitsanos
+ */
+pub fn parse_json5(contents: &str) -> Result<Value> {
+    json5::from_str(contents).map_err(|err| {
+        let json5::Error::Message { msg, location } = err;
+        // pest renders a multi-line report; its last line carries the actual expectation
+        let summary = msg
+            .lines()
+            .last()
+            .map(|line| line.trim().trim_start_matches("= "))
+            .unwrap_or_default();
+        match location {
+            Some(location) => TeraclioError::InvalidInput(format!(
+                "JSON5 parsing error at line {} column {}: {summary}",
+                location.line, location.column
+            )),
+            None => TeraclioError::InvalidInput(format!("JSON5 parsing error: {summary}")),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_json5;
+    use serde_json::json;
+
+    #[test]
+    fn accepts_comments_trailing_commas_and_relaxed_quoting() {
+        let contents =
+            "{\n  // service settings\n  name: 'api', /* inline */\n  ports: [80, 443,],\n}\n";
+        let result = parse_json5(contents).expect("parse");
+        assert_eq!(result, json!({"name": "api", "ports": [80, 443]}));
+    }
+
+    #[test]
+    fn reports_line_and_column() {
+        let err = parse_json5("{\n  name: 'api',\n  port: ,\n}").expect_err("error");
+        assert!(err.to_string().contains("line 3 column 9"), "{err}");
+    }
+}
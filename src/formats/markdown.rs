@@ -0,0 +1,212 @@
+use crate::error::{Result, TeraclioError};
+use crate::formats::toml::table_to_json;
+use crate::formats::yaml::{parse_yaml, YamlDocuments};
+use pulldown_cmark::{html, Options, Parser};
+use serde_json::{Map, Value};
+use std::cmp::Ordering;
+
+/**
+ * Key holding the Markdown body of a document
+ * @author: skitsanos
+ */
+pub const CONTENT_KEY: &str = "content";
+
+/**
+ * Key holding the rendered HTML body when HTML rendering is enabled
+ * @author: skitsanos
+ */
+pub const HTML_KEY: &str = "html";
+
+/**
+ * Options controlling how Markdown data sources are converted
+ * @author: skitsanos
+ */
+#[derive(Clone, Debug, Default)]
+pub struct MarkdownOptions {
+    /** Also render the body to HTML under `html` */
+    pub render_html: bool,
+}
+
+/**
+ * Convert a Markdown document into an object: the fields of its front matter
+ * (YAML between `---` lines or TOML between `+++` lines) plus the body under
+ * `content`. A document without front matter only has `content`.
+ * @author: skitsanos
+ */
+pub fn parse_markdown(contents: &str, options: &MarkdownOptions) -> Result<Value> {
+    let contents = contents.strip_prefix('\u{feff}').unwrap_or(contents);
+    let (front_matter, body) = split_front_matter(contents)?;
+
+    let mut map = match front_matter {
+        None => Map::new(),
+        Some((FrontMatter::Yaml, text)) => match parse_yaml(text, YamlDocuments::Auto)? {
+            Value::Object(map) => map,
+            Value::Null => Map::new(),
+            _ => return Err(front_matter_error("front matter must be a mapping")),
+        },
+        Some((FrontMatter::Toml, text)) => toml::from_str(text)
+            .map(table_to_json)
+            .map_err(|e| front_matter_error(&format!("TOML parsing error: {e}")))?,
+    };
+
+    if options.render_html {
+        let mut rendered = String::new();
+        html::push_html(&mut rendered, Parser::new_ext(body, Options::all()));
+        map.insert(HTML_KEY.to_string(), Value::String(rendered));
+    }
+    map.insert(CONTENT_KEY.to_string(), Value::String(body.to_string()));
+    Ok(Value::Object(map))
+}
+
+/**
+ * Build the array of a Markdown collection from its documents and their file
+ * stems. Each document gets a `slug` (the file stem) unless its front matter
+ * sets one. With a `sort` field (`-field` for descending) the array is ordered
+ * by that field, otherwise the given order is kept.
+ * @author: skitsanos
+ */
+pub fn document_list(documents: Vec<(String, Value)>, sort: Option<&str>) -> Value {
+    let mut documents: Vec<Value> = documents
+        .into_iter()
+        .map(|(stem, mut document)| {
+            if let Value::Object(map) = &mut document {
+                map.entry("slug").or_insert_with(|| Value::String(stem));
+            }
+            document
+        })
+        .collect();
+
+    if let Some(sort) = sort {
+        let (field, descending) = match sort.strip_prefix('-') {
+            Some(field) => (field, true),
+            None => (sort, false),
+        };
+        // Stable sort, so documents with equal values keep their order
+        documents.sort_by(|a, b| compare_field(a.get(field), b.get(field), descending));
+    }
+    Value::Array(documents)
+}
+
+/**
+ * Order two optional field values: numbers numerically, everything else by its
+ * text. Missing and null values sort last in either direction.
+ * @author: skitsanos
+ */
+fn compare_field(a: Option<&Value>, b: Option<&Value>, descending: bool) -> Ordering {
+    let present = |value: Option<&Value>| value.filter(|value| !value.is_null()).cloned();
+    let ordering = match (present(a), present(b)) {
+        (None, None) => return Ordering::Equal,
+        (None, Some(_)) => return Ordering::Greater,
+        (Some(_), None) => return Ordering::Less,
+        (Some(Value::Number(a)), Some(Value::Number(b))) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Some(a), Some(b)) => {
+            let text = |value: Value| match value {
+                Value::String(text) => text,
+                other => other.to_string(),
+            };
+            text(a).cmp(&text(b))
+        }
+    };
+    if descending {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+#[derive(Clone, Copy)]
+enum FrontMatter {
+    Yaml,
+    Toml,
+}
+
+/**
+ * Split the front matter block off the document. The opening fence must be the
+ * very first line, and the closing fence a line of its own.
+ * @author: skitsanos
+ */
+fn split_front_matter(contents: &str) -> Result<(Option<(FrontMatter, &str)>, &str)> {
+    let (kind, fence) = match contents.lines().next().map(str::trim_end) {
+        Some("---") => (FrontMatter::Yaml, "---"),
+        Some("+++") => (FrontMatter::Toml, "+++"),
+        _ => return Ok((None, contents)),
+    };
+
+    let start = contents
+        .find('\n')
+        .map_or(contents.len(), |index| index + 1);
+    let mut offset = start;
+    for line in contents[start..].split_inclusive('\n') {
+        if line.trim_end() == fence {
+            let front_matter = &contents[start..offset];
+            let body = &contents[offset + line.len()..];
+            return Ok((
+                Some((kind, front_matter)),
+                body.trim_start_matches(['\r', '\n']),
+            ));
+        }
+        offset += line.len();
+    }
+
+    Err(front_matter_error(&format!(
+        "missing closing '{fence}' line"
+    )))
+}
+
+fn front_matter_error(reason: &str) -> TeraclioError {
+    TeraclioError::InvalidInput(format!("Markdown front matter error: {reason}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{document_list, parse_markdown, MarkdownOptions};
+    use serde_json::json;
+
+    #[test]
+    fn parses_yaml_and_toml_front_matter() {
+        let yaml = "---\ntitle: Hello\ntags: [a, b]\n---\n\n# Hi\n";
+        assert_eq!(
+            parse_markdown(yaml, &MarkdownOptions::default()).expect("parse"),
+            json!({"title": "Hello", "tags": ["a", "b"], "content": "# Hi\n"})
+        );
+
+        let toml = "+++\ntitle = \"Hello\"\ndraft = true\n+++\nBody\n";
+        let options = MarkdownOptions { render_html: true };
+        assert_eq!(
+            parse_markdown(toml, &options).expect("parse"),
+            json!({"title": "Hello", "draft": true, "content": "Body\n", "html": "<p>Body</p>\n"})
+        );
+    }
+
+    #[test]
+    fn handles_documents_without_or_with_broken_front_matter() {
+        assert_eq!(
+            parse_markdown("Just text\n", &MarkdownOptions::default()).expect("parse"),
+            json!({"content": "Just text\n"})
+        );
+
+        let err =
+            parse_markdown("---\ntitle: x\n", &MarkdownOptions::default()).expect_err("error");
+        assert!(err.to_string().contains("missing closing '---'"), "{err}");
+    }
+
+    #[test]
+    fn sorts_documents_by_field_and_adds_slugs() {
+        let documents = vec![
+            ("a".to_string(), json!({"n": 2})),
+            ("b".to_string(), json!({"n": 10, "slug": "custom"})),
+            ("c".to_string(), json!({})),
+        ];
+        assert_eq!(
+            document_list(documents.clone(), Some("n")),
+            json!([{"n": 2, "slug": "a"}, {"n": 10, "slug": "custom"}, {"slug": "c"}])
+        );
+        assert_eq!(
+            document_list(documents, Some("-n")),
+            json!([{"n": 10, "slug": "custom"}, {"n": 2, "slug": "a"}, {"slug": "c"}])
+        );
+    }
+}
@@ -0,0 +1,100 @@
+/**
+ * Data source format parsers producing serde_json::Value
+ * @author: skitsanos
+ */
+pub mod csv;
+pub mod dotenv;
+pub mod ini;
+pub mod json5;
+pub mod markdown;
+pub mod ndjson;
+pub mod properties;
+pub mod sqlite;
+pub mod toml;
+pub mod xml;
+pub mod yaml;
+
+use crate::error::{Result, TeraclioError};
+use serde_json::{Map, Number, Value};
+
+/**
+ * Interpret text as a typed scalar: `true`/`false`, `null`, or a number. Numbers
+ * are only inferred when they print back identically, so values like "007" or
+ * "1.50" stay strings. Anything else is returned as a string.
+ * @author: skitsanos
+ */
+pub fn infer_scalar(text: &str) -> Value {
+    match text {
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        "null" => return Value::Null,
+        _ => {}
+    }
+
+    if let Ok(int) = text.parse::<i64>() {
+        if int.to_string() == text {
+            return Value::Number(int.into());
+        }
+    }
+
+    if let Ok(float) = text.parse::<f64>() {
+        if float.to_string() == text {
+            if let Some(number) = Number::from_f64(float) {
+                return Value::Number(number);
+            }
+        }
+    }
+
+    Value::String(text.to_string())
+}
+
+/**
+ * Insert a string value into a map, optionally splitting the key on '.' into
+ * nested objects. A key that is both a value and a parent of other keys
+ * (`a=1` and `a.b=2`) is reported as an error instead of silently dropping one.
+ * @author: skitsanos
+ */
+pub fn insert_key(
+    map: &mut Map<String, Value>,
+    key: &str,
+    value: Value,
+    expand_dotted: bool,
+) -> Result<()> {
+    if !expand_dotted {
+        map.insert(key.to_string(), value);
+        return Ok(());
+    }
+
+    let mut segments = key.split('.').peekable();
+    let mut current = map;
+    while let Some(segment) = segments.next() {
+        if segments.peek().is_none() {
+            match (current.get_mut(segment), value) {
+                (Some(Value::Object(existing)), Value::Object(incoming)) => {
+                    existing.extend(incoming)
+                }
+                (Some(Value::Object(_)), _) => return Err(dotted_conflict(key)),
+                (_, value) => {
+                    current.insert(segment.to_string(), value);
+                }
+            }
+            return Ok(());
+        }
+
+        let entry = current
+            .entry(segment.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+        current = match entry {
+            Value::Object(child) => child,
+            _ => return Err(dotted_conflict(key)),
+        };
+    }
+
+    Ok(())
+}
+
+fn dotted_conflict(key: &str) -> TeraclioError {
+    TeraclioError::InvalidInput(format!(
+        "Cannot expand dotted key '{key}': a parent key already holds a plain value"
+    ))
+}
@@ -0,0 +1,79 @@
+use crate::error::{Result, TeraclioError};
+use serde_json::Value;
+use std::io::BufRead;
+
+/**
+ * Streaming reader yielding one JSON value per non-blank line
+ * @author: skitsanos
+ */
+pub struct NdjsonRecords<R: BufRead> {
+    reader: R,
+    line_number: usize,
+    buffer: String,
+}
+
+impl<R: BufRead> NdjsonRecords<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line_number: 0,
+            buffer: String::new(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for NdjsonRecords<R> {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buffer.clear();
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => return None,
+                Ok(_) => self.line_number += 1,
+                Err(e) => return Some(Err(TeraclioError::IoError(e))),
+            }
+
+            let line = self.buffer.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            return Some(serde_json::from_str(line).map_err(|e| {
+                TeraclioError::InvalidInput(format!(
+                    "NDJSON parsing error on line {}: {e}",
+                    self.line_number
+                ))
+            }));
+        }
+    }
+}
+
+/**
+ * Parse a whole NDJSON document into an array of records
+ * @author: skitsanos
+ */
+pub fn parse_ndjson(contents: &str) -> Result<Value> {
+    let records = NdjsonRecords::new(contents.as_bytes()).collect::<Result<Vec<_>>>()?;
+    Ok(Value::Array(records))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_ndjson, NdjsonRecords};
+    use serde_json::json;
+
+    #[test]
+    fn skips_blank_lines() {
+        let result = parse_ndjson("{\"id\":1}\n\n{\"id\":2}\n").expect("parse");
+        assert_eq!(result, json!([{"id": 1}, {"id": 2}]));
+    }
+
+    #[test]
+    fn reports_line_number_of_invalid_record() {
+        let mut records = NdjsonRecords::new("{\"id\":1}\n\n{oops}\n".as_bytes());
+        assert!(records.next().expect("first").is_ok());
+        let err = records.next().expect("second").expect_err("error");
+        assert!(err.to_string().contains("line 3"), "{err}");
+    }
+}
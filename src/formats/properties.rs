@@ -0,0 +1,52 @@
+use crate::error::{Result, TeraclioError};
+use crate::formats::insert_key;
+use serde_json::{Map, Value};
+
+/**
+ * Parse a Java .properties file. Separators, line continuations and
+ * `\uXXXX` escapes follow java.util.Properties. With `expand_dotted`
+ * a key like `db.pool.size` becomes `{"db": {"pool": {"size": ...}}}`.
+ * @author: skitsanos
+ */
+pub fn parse_properties(contents: &str, expand_dotted: bool) -> Result<Value> {
+    let properties = java_properties::read(contents.as_bytes())
+        .map_err(|e| TeraclioError::InvalidInput(format!("Properties parsing error: {e}")))?;
+
+    // Sort keys so that conflicts are reported deterministically
+    let mut entries: Vec<(String, String)> = properties.into_iter().collect();
+    entries.sort();
+
+    let mut map = Map::new();
+    for (key, value) in entries {
+        insert_key(&mut map, &key, Value::String(value), expand_dotted)?;
+    }
+    Ok(Value::Object(map))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_properties;
+    use serde_json::json;
+
+    #[test]
+    fn parses_separators_and_continuations() {
+        let contents = "# comment\napp.name=demo\napp.title : Hello \\\n    World\n";
+        let result = parse_properties(contents, false).expect("parse");
+        assert_eq!(
+            result,
+            json!({"app.name": "demo", "app.title": "Hello World"})
+        );
+    }
+
+    #[test]
+    fn expands_dotted_keys_and_reports_conflicts() {
+        let result = parse_properties("db.host=x\ndb.port=1\n", true).expect("parse");
+        assert_eq!(result, json!({"db": {"host": "x", "port": "1"}}));
+
+        let err = parse_properties("db=x\ndb.port=1\n", true).expect_err("error");
+        assert!(
+            err.to_string().contains("Cannot expand dotted key"),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use crate::error::{Result, TeraclioError};
+use crate::utils::{parse_data_source, ParseOptions};
+use base64::{engine::general_purpose, Engine as _};
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+use serde_json::{Map, Number, Value};
+use std::path::Path;
+
+/**
+ * Read a sqlite: source: the rows of `query`, or an object with the rows of
+ * each query in the `queries` file, which maps query names to SQL strings
+ * @author: skitsanos
+ */
+pub fn load_sqlite(database: &str, query: Option<&str>, queries: Option<&str>) -> Result<Value> {
+    if let Some(query) = query {
+        return query_sqlite(database, query);
+    }
+
+    let file = queries.unwrap_or_default();
+    let queries = match parse_data_source(file, &ParseOptions::default())? {
+        Value::Object(map) => map
+            .into_iter()
+            .map(|(name, query)| match query {
+                Value::String(query) => Ok((name, query)),
+                _ => Err(TeraclioError::InvalidInput(format!(
+                    "Query '{name}' in '{file}' must be a string"
+                ))),
+            })
+            .collect::<Result<Vec<_>>>()?,
+        _ => {
+            return Err(TeraclioError::InvalidInput(format!(
+                "Queries file '{file}' must map query names to SQL strings"
+            )))
+        }
+    };
+    query_sqlite_named(database, &queries)
+}
+
+/**
+ * Run one read-only query and return its rows as an array of objects
+ * @author: skitsanos
+ */
+fn query_sqlite(path: &str, query: &str) -> Result<Value> {
+    let connection = open_read_only(path)?;
+    query_rows(&connection, path, query)
+}
+
+/**
+ * Run several named read-only queries and return an object mapping each
+ * name to its rows
+ * @author: skitsanos
+ */
+fn query_sqlite_named(path: &str, queries: &[(String, String)]) -> Result<Value> {
+    let connection = open_read_only(path)?;
+    let mut map = Map::new();
+    for (name, query) in queries {
+        let rows = query_rows(&connection, path, query).map_err(|e| match e {
+            TeraclioError::InvalidInput(message) => {
+                TeraclioError::InvalidInput(format!("{message} (query '{name}')"))
+            }
+            other => other,
+        })?;
+        map.insert(name.clone(), rows);
+    }
+    Ok(Value::Object(map))
+}
+
+fn open_read_only(path: &str) -> Result<Connection> {
+    if !Path::new(path).is_file() {
+        return Err(TeraclioError::InvalidInput(format!(
+            "Data source file does not exist: {path}"
+        )));
+    }
+    Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )
+    .map_err(|e| sqlite_error(path, e))
+}
+
+/**
+ * Convert the rows of a query into objects keyed by column name. Integers and
+ * reals become numbers, text becomes strings and blobs are base64-encoded.
+ * @author: skitsanos
+ */
+fn query_rows(connection: &Connection, path: &str, query: &str) -> Result<Value> {
+    let mut statement = connection
+        .prepare(query)
+        .map_err(|e| sqlite_error(path, e))?;
+    if !statement.readonly() {
+        return Err(TeraclioError::InvalidInput(format!(
+            "SQLite error in '{path}': only read-only queries are allowed"
+        )));
+    }
+
+    let columns: Vec<String> = statement
+        .column_names()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let mut rows = statement.query([]).map_err(|e| sqlite_error(path, e))?;
+
+    let mut records = Vec::new();
+    while let Some(row) = rows.next().map_err(|e| sqlite_error(path, e))? {
+        let mut record = Map::new();
+        for (index, column) in columns.iter().enumerate() {
+            let value = row.get_ref(index).map_err(|e| sqlite_error(path, e))?;
+            record.insert(column.clone(), convert_value(value));
+        }
+        records.push(Value::Object(record));
+    }
+    Ok(Value::Array(records))
+}
+
+fn convert_value(value: ValueRef) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(int) => Value::Number(int.into()),
+        ValueRef::Real(float) => Number::from_f64(float).map_or(Value::Null, Value::Number),
+        ValueRef::Text(text) => Value::String(String::from_utf8_lossy(text).into_owned()),
+        ValueRef::Blob(blob) => Value::String(general_purpose::STANDARD.encode(blob)),
+    }
+}
+
+fn sqlite_error(path: &str, err: rusqlite::Error) -> TeraclioError {
+    TeraclioError::InvalidInput(format!("SQLite error in '{path}': {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load_sqlite, query_sqlite};
+    use crate::utils::test_support::{unique_path, write_temp_file};
+    use rusqlite::Connection;
+    use serde_json::json;
+
+    fn database() -> String {
+        let path = unique_path("db");
+        let connection = Connection::open(&path).expect("create database");
+        connection
+            .execute_batch(
+                "CREATE TABLE hosts (name TEXT, cpus INTEGER, load REAL, key BLOB, note TEXT);
+                 INSERT INTO hosts VALUES ('web', 4, 0.5, x'ff00', NULL);",
+            )
+            .expect("seed database");
+        path.to_str().expect("utf8").to_string()
+    }
+
+    #[test]
+    fn maps_column_types_to_json() {
+        let path = database();
+        let rows = query_sqlite(&path, "SELECT * FROM hosts").expect("query");
+        let queries = write_temp_file("yaml", "count: SELECT count(*) AS n FROM hosts\n");
+        let named = load_sqlite(&path, None, queries.to_str()).expect("named");
+        std::fs::remove_file(&path).expect("cleanup");
+        std::fs::remove_file(&queries).expect("cleanup");
+
+        assert_eq!(
+            rows,
+            json!([{"name": "web", "cpus": 4, "load": 0.5, "key": "/wA=", "note": null}])
+        );
+        assert_eq!(named, json!({"count": [{"n": 1}]}));
+    }
+
+    #[test]
+    fn refuses_writes() {
+        let path = database();
+        let err = query_sqlite(&path, "DELETE FROM hosts").expect_err("error");
+        std::fs::remove_file(&path).expect("cleanup");
+        assert!(err.to_string().contains("read-only"), "{err}");
+    }
+}
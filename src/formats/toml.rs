@@ -0,0 +1,56 @@
+use crate::error::{Result, TeraclioError};
+use serde_json::{Map, Number, Value};
+
+/**
+ * Parse a TOML document into a JSON object. Dates and times are converted to
+ * their TOML text (e.g. `2024-03-01` or `2024-03-01T10:00:00Z`), so they can
+ * be compared and formatted like date strings from other formats.
+ * @author: skitsanos
+ */
+pub fn parse_toml(contents: &str) -> Result<Value> {
+    ::toml::from_str(contents)
+        .map(|table| Value::Object(table_to_json(table)))
+        .map_err(|e| TeraclioError::InvalidInput(format!("TOML parsing error: {e}")))
+}
+
+/**
+ * Convert a parsed TOML table into a JSON map, with dates and times as strings
+ * @author: skitsanos
+ */
+pub fn table_to_json(table: ::toml::Table) -> Map<String, Value> {
+    table
+        .into_iter()
+        .map(|(key, value)| (key, toml_to_json(value)))
+        .collect()
+}
+
+fn toml_to_json(value: ::toml::Value) -> Value {
+    match value {
+        ::toml::Value::String(text) => Value::String(text),
+        ::toml::Value::Integer(int) => Value::Number(int.into()),
+        ::toml::Value::Float(float) => Number::from_f64(float).map_or(Value::Null, Value::Number),
+        ::toml::Value::Boolean(flag) => Value::Bool(flag),
+        ::toml::Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        ::toml::Value::Array(items) => Value::Array(items.into_iter().map(toml_to_json).collect()),
+        ::toml::Value::Table(table) => Value::Object(table_to_json(table)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_toml;
+    use serde_json::json;
+
+    #[test]
+    fn converts_dates_and_times_to_strings() {
+        let contents = "day = 2024-03-01\nat = 1979-05-27T07:32:00Z\n[[events]]\ntime = 07:32:00\n";
+        assert_eq!(
+            parse_toml(contents).expect("parse"),
+            json!({
+                "day": "2024-03-01",
+                "at": "1979-05-27T07:32:00Z",
+                "events": [{"time": "07:32:00"}]
+            })
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use crate::error::{Result, TeraclioError};
+use roxmltree::{Document, Node};
+use serde_json::{Map, Value};
+
+/**
+ * Prefix applied to attribute names in the converted object
+ * @author: skitsanos
+ */
+pub const ATTRIBUTE_PREFIX: &str = "@";
+
+/**
+ * Key holding the text content of elements that also have attributes or children
+ * @author: skitsanos
+ */
+pub const TEXT_KEY: &str = "#text";
+
+/**
+ * Options controlling how XML data sources are converted
+ * @author: skitsanos
+ */
+#[derive(Clone, Debug, Default)]
+pub struct XmlOptions {
+    /**
+     * Dotted element paths from the root (e.g. `project.dependencies.dependency`)
+     * that always become arrays, even when the element occurs only once.
+     */
+    pub force_arrays: Vec<String>,
+}
+
+/**
+ * Convert an XML document into a serde_json::Value using these rules:
+ *
+ * * The result is an object with the root element name as its only key.
+ * * Attributes become keys prefixed with `@`.
+ * * Child elements become keys named after their local tag name; repeated
+ *   siblings are collapsed into an array in document order.
+ * * An element with neither attributes nor child elements becomes its trimmed
+ *   text as a string (an empty string for empty elements).
+ * * Otherwise, non-blank text content is stored under `#text`.
+ * * Paths listed in `force_arrays` are always arrays.
+ *
+ * Namespace prefixes are dropped, so `<pom:version>` is read as `version`.
+ * @author: skitsanos
+ */
+pub fn parse_xml(contents: &str, options: &XmlOptions) -> Result<Value> {
+    let document = Document::parse(contents)
+        .map_err(|e| TeraclioError::InvalidInput(format!("XML parsing error: {e}")))?;
+
+    let root = document.root_element();
+    let name = root.tag_name().name().to_string();
+    let value = convert_element(root, &name, options);
+
+    let mut map = Map::new();
+    if is_forced_array(&name, options) {
+        map.insert(name, Value::Array(vec![value]));
+    } else {
+        map.insert(name, value);
+    }
+    Ok(Value::Object(map))
+}
+
+fn convert_element(node: Node, path: &str, options: &XmlOptions) -> Value {
+    let mut map = Map::new();
+
+    for attribute in node.attributes() {
+        map.insert(
+            format!("{ATTRIBUTE_PREFIX}{}", attribute.name()),
+            Value::String(attribute.value().to_string()),
+        );
+    }
+
+    let mut text = String::new();
+    for child in node.children() {
+        if child.is_element() {
+            let name = child.tag_name().name().to_string();
+            let child_path = format!("{path}.{name}");
+            let value = convert_element(child, &child_path, options);
+
+            match map.get_mut(&name) {
+                // Element values are never arrays themselves, so an array here
+                // means the name was already collapsed or forced.
+                Some(Value::Array(items)) => items.push(value),
+                Some(existing) => {
+                    let first = existing.take();
+                    *existing = Value::Array(vec![first, value]);
+                }
+                None => {
+                    let value = if is_forced_array(&child_path, options) {
+                        Value::Array(vec![value])
+                    } else {
+                        value
+                    };
+                    map.insert(name, value);
+                }
+            }
+        } else if child.is_text() {
+            text.push_str(child.text().unwrap_or_default());
+        }
+    }
+
+    let text = text.trim();
+    if map.is_empty() {
+        return Value::String(text.to_string());
+    }
+    if !text.is_empty() {
+        map.insert(TEXT_KEY.to_string(), Value::String(text.to_string()));
+    }
+    Value::Object(map)
+}
+
+fn is_forced_array(path: &str, options: &XmlOptions) -> bool {
+    options.force_arrays.iter().any(|forced| forced == path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_xml, XmlOptions};
+    use serde_json::json;
+
+    #[test]
+    fn maps_attributes_text_and_repeated_elements() {
+        let xml = r#"<project version="4"><name>demo</name><tag lang="en">hi</tag>
+            <module>a</module><module>b</module></project>"#;
+        let result = parse_xml(xml, &XmlOptions::default()).expect("parse");
+        assert_eq!(
+            result,
+            json!({"project": {
+                "@version": "4",
+                "name": "demo",
+                "tag": {"@lang": "en", "#text": "hi"},
+                "module": ["a", "b"]
+            }})
+        );
+    }
+
+    #[test]
+    fn forces_single_elements_into_arrays() {
+        let options = XmlOptions {
+            force_arrays: vec!["deps.dep".to_string()],
+        };
+        let result = parse_xml("<deps><dep>x</dep></deps>", &options).expect("parse");
+        assert_eq!(result, json!({"deps": {"dep": ["x"]}}));
+    }
+
+    #[test]
+    fn reports_malformed_documents() {
+        let err = parse_xml("<a><b></a>", &XmlOptions::default()).expect_err("error");
+        assert!(err.to_string().contains("XML parsing error"), "{err}");
+    }
+}
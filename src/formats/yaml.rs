@@ -0,0 +1,246 @@
+use crate::error::{Result, TeraclioError};
+use crate::include::INCLUDE_KEY;
+use serde::Deserialize;
+use serde_json::Value;
+
+/**
+ * How a YAML stream with several `---` documents is exposed
+ * @author: skitsanos
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum YamlDocuments {
+    /** A single document is used as-is, several documents become an array */
+    #[default]
+    Auto,
+    /** The documents always become an array, even when there is only one */
+    Array,
+}
+
+/**
+ * Parse YAML into a serde_json::Value according to the document mode. In `Auto`
+ * mode a stream without documents (empty or only comments) is null.
+ * @author: skitsanos
+ */
+pub fn parse_yaml(contents: &str, documents: YamlDocuments) -> Result<Value> {
+    let mut values = parse_yaml_documents(contents)?;
+    match (documents, values.len()) {
+        (YamlDocuments::Auto, 0) => Ok(Value::Null),
+        (YamlDocuments::Auto, 1) => Ok(values.remove(0)),
+        _ => Ok(Value::Array(values)),
+    }
+}
+
+/**
+ * Parse every document of a YAML stream. Documents that are explicitly null are
+ * kept; the empty document left by a trailing `---` separator, or by a stream
+ * with no content, is not.
+ * @author: skitsanos
+ */
+pub fn parse_yaml_documents(contents: &str) -> Result<Vec<Value>> {
+    let mut values = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(contents) {
+        let mut yaml = serde_yaml::Value::deserialize(document).map_err(yaml_error)?;
+        apply_merge_keys(&mut yaml)?;
+        include_tags_to_refs(&mut yaml);
+        stringify_keys(&mut yaml);
+        values.push(
+            serde_json::to_value(yaml)
+                .map_err(|e| TeraclioError::InvalidInput(format!("YAML parsing error: {e}")))?,
+        );
+    }
+    if values.last() == Some(&Value::Null) && ends_without_document(contents) {
+        values.pop();
+    }
+    Ok(values)
+}
+
+/**
+ * Whether the stream ends with nothing but a `---` separator, blank lines and
+ * comments. The parser reports that missing document as null, the same as `~`.
+ * @author: skitsanos
+ */
+fn ends_without_document(contents: &str) -> bool {
+    let last = contents.lines().rev().find(|line| {
+        let line = line.trim();
+        !line.is_empty() && !line.starts_with('#')
+    });
+    match last {
+        None => true,
+        Some(line) => line.strip_prefix("---").is_some_and(|rest| {
+            let rest = rest.trim();
+            rest.is_empty() || rest.starts_with('#')
+        }),
+    }
+}
+
+/**
+ * Apply YAML 1.1 merge keys (`<<: *defaults` or `<<: [*a, *b]`). Keys written in
+ * the mapping itself win over merged keys, and earlier mappings in a merge list
+ * win over later ones. Merge sources are resolved first, so chained merges work.
+ * @author: skitsanos
+ */
+fn apply_merge_keys(value: &mut serde_yaml::Value) -> Result<()> {
+    match value {
+        serde_yaml::Value::Mapping(mapping) => {
+            let merge = mapping.remove("<<");
+            for item in mapping.values_mut() {
+                apply_merge_keys(item)?;
+            }
+
+            let sources = match merge {
+                None => return Ok(()),
+                Some(serde_yaml::Value::Sequence(sources)) => sources,
+                Some(source) => vec![source],
+            };
+            for mut source in sources {
+                apply_merge_keys(&mut source)?;
+                let serde_yaml::Value::Mapping(source) = source else {
+                    return Err(TeraclioError::InvalidInput(
+                        "YAML parsing error: the merge key '<<' must refer to a mapping or a list of mappings"
+                            .to_string(),
+                    ));
+                };
+                for (key, item) in source {
+                    mapping.entry(key).or_insert(item);
+                }
+            }
+            Ok(())
+        }
+        serde_yaml::Value::Sequence(items) => items.iter_mut().try_for_each(apply_merge_keys),
+        serde_yaml::Value::Tagged(tagged) => apply_merge_keys(&mut tagged.value),
+        _ => Ok(()),
+    }
+}
+
+/**
+ * Rewrite `!include path` tags as `{"$include": "path"}` objects. They are
+ * resolved together with `$ref` objects after parsing, but unlike `$ref` the
+ * file is always loaded.
+ * @author: skitsanos
+ */
+fn include_tags_to_refs(value: &mut serde_yaml::Value) {
+    match value {
+        serde_yaml::Value::Tagged(tagged) if tagged.tag == "!include" => {
+            let mut reference = serde_yaml::Mapping::new();
+            reference.insert(INCLUDE_KEY.into(), tagged.value.clone());
+            *value = serde_yaml::Value::Mapping(reference);
+        }
+        serde_yaml::Value::Tagged(tagged) => include_tags_to_refs(&mut tagged.value),
+        serde_yaml::Value::Sequence(items) => items.iter_mut().for_each(include_tags_to_refs),
+        serde_yaml::Value::Mapping(map) => map.values_mut().for_each(include_tags_to_refs),
+        _ => {}
+    }
+}
+
+/**
+ * Turn null, boolean and number mapping keys into strings (`~: x` becomes the
+ * key "null"), since JSON object keys must be strings
+ * @author: skitsanos
+ */
+fn stringify_keys(value: &mut serde_yaml::Value) {
+    match value {
+        serde_yaml::Value::Mapping(mapping) => {
+            *mapping = std::mem::take(mapping)
+                .into_iter()
+                .map(|(key, mut item)| {
+                    stringify_keys(&mut item);
+                    let key = match key {
+                        serde_yaml::Value::Null => "null".into(),
+                        serde_yaml::Value::Bool(flag) => flag.to_string().into(),
+                        serde_yaml::Value::Number(number) => number.to_string().into(),
+                        other => other,
+                    };
+                    (key, item)
+                })
+                .collect();
+        }
+        serde_yaml::Value::Sequence(items) => items.iter_mut().for_each(stringify_keys),
+        serde_yaml::Value::Tagged(tagged) => stringify_keys(&mut tagged.value),
+        _ => {}
+    }
+}
+
+fn yaml_error(err: serde_yaml::Error) -> TeraclioError {
+    TeraclioError::InvalidInput(format!("YAML parsing error: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_yaml, YamlDocuments};
+    use serde_json::json;
+
+    #[test]
+    fn single_document_is_unwrapped_unless_array_is_requested() {
+        let auto = parse_yaml("name: a\n", YamlDocuments::Auto).expect("parse");
+        assert_eq!(auto, json!({"name": "a"}));
+
+        let array = parse_yaml("name: a\n", YamlDocuments::Array).expect("parse");
+        assert_eq!(array, json!([{"name": "a"}]));
+    }
+
+    #[test]
+    fn multiple_documents_become_an_array() {
+        let contents = "kind: Service\n---\nkind: Deployment\n---\n";
+        let result = parse_yaml(contents, YamlDocuments::Auto).expect("parse");
+        assert_eq!(result, json!([{"kind": "Service"}, {"kind": "Deployment"}]));
+    }
+
+    #[test]
+    fn keeps_null_documents_and_reads_an_empty_stream_as_null() {
+        let result =
+            parse_yaml("a: 1\n---\n~\n---\nnull\n---\n", YamlDocuments::Auto).expect("parse");
+        assert_eq!(result, json!([{"a": 1}, null, null]));
+
+        assert_eq!(
+            parse_yaml("# only a comment\n", YamlDocuments::Auto).expect("parse"),
+            json!(null)
+        );
+        assert_eq!(
+            parse_yaml("~\n", YamlDocuments::Auto).expect("parse"),
+            json!(null)
+        );
+        assert_eq!(
+            parse_yaml("", YamlDocuments::Array).expect("parse"),
+            json!([])
+        );
+    }
+
+    #[test]
+    fn null_bool_and_number_keys_become_strings() {
+        let result = parse_yaml(
+            "~: a\ntrue: b\n1: c\nnested:\n  null: d\n",
+            YamlDocuments::Auto,
+        )
+        .expect("parse");
+        assert_eq!(
+            result,
+            json!({"null": "a", "true": "b", "1": "c", "nested": {"null": "d"}})
+        );
+    }
+
+    #[test]
+    fn applies_merge_keys_with_yaml_precedence() {
+        let contents = "\
+base: &base {host: localhost, port: 80, tls: false}
+extra: &extra {port: 8080, debug: true}
+chained: &chained
+  <<: *base
+  region: eu
+service:
+  <<: [*extra, *chained]
+  tls: true
+";
+        let result = parse_yaml(contents, YamlDocuments::Auto).expect("parse");
+        assert_eq!(
+            result["service"],
+            json!({"host": "localhost", "port": 8080, "tls": true, "debug": true, "region": "eu"})
+        );
+        assert_eq!(
+            result["chained"],
+            json!({"host": "localhost", "port": 80, "tls": false, "region": "eu"})
+        );
+
+        let err = parse_yaml("a:\n  <<: 1\n", YamlDocuments::Auto).expect_err("error");
+        assert!(err.to_string().contains("merge key"), "{err}");
+    }
+}
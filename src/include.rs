@@ -0,0 +1,316 @@
+use crate::error::{Result, TeraclioError};
+use crate::source::Selector;
+use crate::utils::{read_data_source, InputFormat, ParseOptions};
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/**
+ * Maximum nesting of included files, counted from the source given on the command line
+ * @author: skitsanos
+ */
+pub const MAX_INCLUDE_DEPTH: usize = 16;
+
+/**
+ * Key of the object a YAML `!include` tag is read as. Unlike `$ref`, the file
+ * it names is always loaded, whatever its extension.
+ * @author: skitsanos
+ */
+pub const INCLUDE_KEY: &str = "$include";
+
+/**
+ * Files pulled in by `$ref` or `!include` while parsing a data source, for the
+ * watch set
+ * @author: skitsanos
+ */
+pub fn included_files(source: &str, options: &ParseOptions) -> Result<Vec<PathBuf>> {
+    let value = read_data_source(source, options)?;
+    let (_, files) = resolve_source(value, source, options)?;
+    Ok(files.into_iter().collect())
+}
+
+/**
+ * Replace `{"$ref": "file#selector"}` objects with the referenced data. YAML
+ * `!include file` tags are read as `{"$include": "file"}` objects and replaced
+ * the same way. Paths are relative to the including file (the working directory
+ * for stdin), and the optional `#selector` is a JSON Pointer or dotted path into
+ * the included data.
+ *
+ * `$include` objects are always replaced. A `$ref` object is only replaced when
+ * it is the single key and its file has a supported data extension; local
+ * references (`#/...`), URLs and other names are left alone, so JSON Schema
+ * documents keep working.
+ * @author: skitsanos
+ */
+pub fn resolve_refs(value: Value, source: &str, options: &ParseOptions) -> Result<Value> {
+    resolve_source(value, source, options).map(|(value, _)| value)
+}
+
+/**
+ * Resolve the references of data read from `source`, returning the resolved
+ * data and every file that was included
+ * @author: skitsanos
+ */
+fn resolve_source(
+    value: Value,
+    source: &str,
+    options: &ParseOptions,
+) -> Result<(Value, BTreeSet<PathBuf>)> {
+    let mut stack = Vec::new();
+    let base = if source == "-" {
+        PathBuf::from(".")
+    } else {
+        let path = Path::new(source);
+        stack.push(path.canonicalize().unwrap_or_else(|_| path.to_path_buf()));
+        parent_dir(path)
+    };
+
+    let mut resolver = Resolver {
+        options: ParseOptions {
+            format: None,
+            ..options.clone()
+        },
+        stack,
+        files: BTreeSet::new(),
+    };
+    let value = resolver.resolve(value, &base)?;
+    Ok((value, resolver.files))
+}
+
+struct Resolver {
+    options: ParseOptions,
+    /** Files currently being included, outermost first */
+    stack: Vec<PathBuf>,
+    /** Every file included so far */
+    files: BTreeSet<PathBuf>,
+}
+
+impl Resolver {
+    fn resolve(&mut self, value: Value, base: &Path) -> Result<Value> {
+        match value {
+            Value::Object(map) => {
+                if let Some(reference) = external_ref(&map)? {
+                    return self.include(reference, base);
+                }
+                map.into_iter()
+                    .map(|(key, item)| Ok((key, self.resolve(item, base)?)))
+                    .collect::<Result<serde_json::Map<_, _>>>()
+                    .map(Value::Object)
+            }
+            Value::Array(items) => items
+                .into_iter()
+                .map(|item| self.resolve(item, base))
+                .collect::<Result<Vec<_>>>()
+                .map(Value::Array),
+            other => Ok(other),
+        }
+    }
+
+    fn include(&mut self, reference: &str, base: &Path) -> Result<Value> {
+        let including = self
+            .stack
+            .last()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| "stdin".to_string());
+        let (file, selector) = match reference.split_once('#') {
+            Some((file, selector)) => (file, Some(selector)),
+            None => (reference, None),
+        };
+
+        let path = base.join(file);
+        let canonical = path.canonicalize().map_err(|e| {
+            TeraclioError::InvalidInput(format!(
+                "Cannot include '{reference}' from '{including}': {}: {e}",
+                path.display()
+            ))
+        })?;
+
+        if let Some(start) = self.stack.iter().position(|entry| *entry == canonical) {
+            let chain: Vec<String> = self.stack[start..]
+                .iter()
+                .chain(std::iter::once(&canonical))
+                .map(|path| path.display().to_string())
+                .collect();
+            return Err(TeraclioError::InvalidInput(format!(
+                "Include cycle: {}",
+                chain.join(" -> ")
+            )));
+        }
+        if self.stack.len() > MAX_INCLUDE_DEPTH {
+            return Err(TeraclioError::InvalidInput(format!(
+                "Cannot include '{reference}' from '{including}': more than {MAX_INCLUDE_DEPTH} levels of nested includes"
+            )));
+        }
+
+        self.files.insert(canonical.clone());
+
+        let path_str = path.to_str().ok_or_else(|| {
+            TeraclioError::InvalidInput(format!("Path is not valid UTF-8: {}", path.display()))
+        })?;
+        let data = read_data_source(path_str, &self.options).map_err(|e| {
+            let reason = match e {
+                TeraclioError::InvalidInput(message) => message,
+                other => other.to_string(),
+            };
+            TeraclioError::InvalidInput(format!(
+                "Cannot include '{reference}' from '{including}': {reason}"
+            ))
+        })?;
+
+        self.stack.push(canonical);
+        let data = self.resolve(data, &parent_dir(&path));
+        self.stack.pop();
+        let data = data?;
+
+        match selector.filter(|selector| !selector.is_empty()) {
+            Some(raw) => {
+                let selector = Selector::parse(raw).map_err(TeraclioError::InvalidInput)?;
+                selector.select(&data).cloned().ok_or_else(|| {
+                    TeraclioError::InvalidInput(format!(
+                        "Selector '#{raw}' of '{reference}' did not match anything in '{}'",
+                        path.display()
+                    ))
+                })
+            }
+            None => Ok(data),
+        }
+    }
+}
+
+/**
+ * The file reference of a `{"$include": ...}` or `{"$ref": ...}` object. An
+ * `$include` must name a file. A `$ref` only counts when its file has a
+ * supported data extension, so local references, URLs and names such as `Pet`
+ * are left in the data untouched.
+ * @author: skitsanos
+ */
+fn external_ref(map: &serde_json::Map<String, Value>) -> Result<Option<&str>> {
+    if map.len() != 1 {
+        return Ok(None);
+    }
+    if let Some(reference) = map.get(INCLUDE_KEY) {
+        return match reference.as_str() {
+            Some(reference) if !reference.is_empty() => Ok(Some(reference)),
+            _ => Err(TeraclioError::InvalidInput(format!(
+                "!include needs a file path, got {reference}"
+            ))),
+        };
+    }
+    let Some(reference) = map.get("$ref").and_then(Value::as_str) else {
+        return Ok(None);
+    };
+    if reference.starts_with('#') || reference.contains("://") {
+        return Ok(None);
+    }
+    let file = reference
+        .split_once('#')
+        .map_or(reference, |(file, _)| file);
+    let path = Path::new(file);
+    let supported =
+        path.extension().is_some() && InputFormat::detect_from_extension(path).is_some();
+    Ok(supported.then_some(reference))
+}
+
+fn parent_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::included_files;
+    use crate::utils::test_support::temp_dir;
+    use crate::utils::{parse_data_source, ParseOptions};
+    use serde_json::json;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn include_dir() -> PathBuf {
+        let dir = temp_dir();
+        fs::create_dir(dir.join("parts")).expect("create parts dir");
+        dir
+    }
+
+    #[test]
+    fn resolves_refs_and_yaml_includes_relative_to_the_including_file() {
+        let dir = include_dir();
+        fs::write(
+            dir.join("main.yaml"),
+            "db: !include parts/db.yaml\nports:\n  $ref: parts/net.json#/ports\nschema:\n  $ref: '#/local'\nmodel:\n  $ref: Pet\n",
+        )
+        .expect("write main");
+        fs::write(
+            dir.join("parts/db.yaml"),
+            "host: x\nuser:\n  $ref: user.json\n",
+        )
+        .expect("write db");
+        fs::write(dir.join("parts/user.json"), r#"{"name": "app"}"#).expect("write user");
+        fs::write(dir.join("parts/net.json"), r#"{"ports": [80, 443]}"#).expect("write net");
+
+        let main = dir.join("main.yaml");
+        let main = main.to_str().expect("utf8");
+        let result = parse_data_source(main, &ParseOptions::default()).expect("parse");
+        let included: Vec<String> = included_files(main, &ParseOptions::default())
+            .expect("included files")
+            .iter()
+            .filter_map(|file| file.file_name()?.to_str().map(str::to_string))
+            .collect();
+        fs::remove_dir_all(dir).expect("cleanup");
+
+        assert_eq!(
+            result,
+            json!({
+                "db": {"host": "x", "user": {"name": "app"}},
+                "ports": [80, 443],
+                "schema": {"$ref": "#/local"},
+                "model": {"$ref": "Pet"}
+            })
+        );
+        assert_eq!(included, ["db.yaml", "net.json", "user.json"]);
+    }
+
+    #[test]
+    fn reports_include_cycles() {
+        let dir = include_dir();
+        fs::write(dir.join("a.json"), r#"{"b": {"$ref": "parts/b.json"}}"#).expect("write a");
+        fs::write(dir.join("parts/b.json"), r#"{"a": {"$ref": "../a.json"}}"#).expect("write b");
+
+        let a = dir.join("a.json");
+        let err = parse_data_source(a.to_str().expect("utf8"), &ParseOptions::default())
+            .expect_err("cycle")
+            .to_string();
+        fs::remove_dir_all(&dir).expect("cleanup");
+
+        assert!(err.contains("Include cycle: "), "{err}");
+        assert!(err.contains("b.json -> "), "{err}");
+        assert!(err.ends_with("a.json"), "{err}");
+    }
+
+    #[test]
+    fn yaml_includes_are_always_loaded() {
+        let dir = include_dir();
+        fs::write(dir.join("notes.txt"), "hello\n").expect("write notes");
+        for (name, contents, expected) in [
+            (
+                "text.yaml",
+                "notes: !include notes.txt\n",
+                "Unsupported input format",
+            ),
+            (
+                "missing.yaml",
+                "notes: !include missing\n",
+                "Cannot include 'missing'",
+            ),
+        ] {
+            let main = dir.join(name);
+            fs::write(&main, contents).expect("write main");
+            let err = parse_data_source(main.to_str().expect("utf8"), &ParseOptions::default())
+                .expect_err("error")
+                .to_string();
+            assert!(err.contains(expected), "{err}");
+        }
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+}
@@ -0,0 +1,245 @@
+use crate::error::{Result, TeraclioError};
+use crate::path::{DataPath, Segment};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/**
+ * Resolve `${...}` references in every string of the data:
+ *
+ * * `${path.to.key}` is replaced by the value at that path of the data itself.
+ * * `${env:NAME}` is replaced by the environment variable `NAME`.
+ * * `${ref:-fallback}` uses the fallback when the reference is missing, null or
+ *   an empty string. The fallback may contain references itself.
+ * * `$${` is a literal `${`.
+ *
+ * A string that consists of a single reference takes the referenced value with its
+ * type (number, object, ...); otherwise values are inserted as text. Referenced
+ * values are resolved first, and reference cycles are reported as errors.
+ * @author: skitsanos
+ */
+pub fn interpolate(data: &mut Value) -> Result<()> {
+    let source = data.clone();
+    let mut resolver = Resolver {
+        data: &source,
+        stack: Vec::new(),
+        resolved: HashMap::new(),
+    };
+    *data = resolver.resolve_value(&source, &DataPath::default())?;
+    Ok(())
+}
+
+struct Resolver<'a> {
+    data: &'a Value,
+    /** Locations whose strings are currently being resolved, outermost first */
+    stack: Vec<String>,
+    /** Referenced values that have been resolved already */
+    resolved: HashMap<DataPath, Value>,
+}
+
+impl Resolver<'_> {
+    fn resolve_value(&mut self, value: &Value, location: &DataPath) -> Result<Value> {
+        match value {
+            Value::String(text) => {
+                let key = location.to_string();
+                if let Some(start) = self.stack.iter().position(|entry| *entry == key) {
+                    let mut chain = self.stack[start..].to_vec();
+                    chain.push(key);
+                    return Err(TeraclioError::InvalidInput(format!(
+                        "Interpolation cycle: {}",
+                        chain.join(" -> ")
+                    )));
+                }
+
+                self.stack.push(key);
+                let resolved = self.resolve_string(text, location);
+                self.stack.pop();
+                resolved
+            }
+            Value::Array(items) => items
+                .iter()
+                .enumerate()
+                .map(|(index, item)| {
+                    self.resolve_value(item, &location.child(Segment::Index(index)))
+                })
+                .collect::<Result<Vec<_>>>()
+                .map(Value::Array),
+            Value::Object(map) => map
+                .iter()
+                .map(|(key, item)| {
+                    let child = location.child(Segment::Key(key.clone()));
+                    Ok((key.clone(), self.resolve_value(item, &child)?))
+                })
+                .collect::<Result<serde_json::Map<_, _>>>()
+                .map(Value::Object),
+            other => Ok(other.clone()),
+        }
+    }
+
+    fn resolve_string(&mut self, text: &str, location: &DataPath) -> Result<Value> {
+        let mut output = String::new();
+        let mut rest = text;
+
+        while let Some(start) = rest.find('$') {
+            output.push_str(&rest[..start]);
+            let after = &rest[start..];
+
+            if let Some(tail) = after.strip_prefix("$${") {
+                output.push_str("${");
+                rest = tail;
+            } else if let Some(tail) = after.strip_prefix("${") {
+                let end = closing_brace(tail).ok_or_else(|| {
+                    TeraclioError::InvalidInput(format!(
+                        "Unterminated '${{' in '{}': {text}",
+                        describe(location)
+                    ))
+                })?;
+                let value = self.resolve_reference(&tail[..end], location)?;
+                let remainder = &tail[end + 1..];
+
+                // A lone reference keeps the type of the referenced value
+                if output.is_empty() && remainder.is_empty() && start == 0 {
+                    return Ok(value);
+                }
+                match value {
+                    Value::String(value) => output.push_str(&value),
+                    Value::Null => {}
+                    other => output.push_str(&other.to_string()),
+                }
+                rest = remainder;
+            } else {
+                output.push('$');
+                rest = &after[1..];
+            }
+        }
+
+        output.push_str(rest);
+        Ok(Value::String(output))
+    }
+
+    fn resolve_reference(&mut self, expression: &str, location: &DataPath) -> Result<Value> {
+        let (name, fallback) = match expression.split_once(":-") {
+            Some((name, fallback)) => (name.trim(), Some(fallback)),
+            None => (expression.trim(), None),
+        };
+
+        let value = match name.strip_prefix("env:") {
+            Some(variable) => std::env::var(variable).ok().map(Value::String),
+            None => {
+                let path = DataPath::parse(name)?;
+                match (self.resolved.get(&path), path.get(self.data)) {
+                    (Some(resolved), _) => Some(resolved.clone()),
+                    (None, Some(value)) => {
+                        let value = self.resolve_value(value, &path)?;
+                        self.resolved.insert(path, value.clone());
+                        Some(value)
+                    }
+                    (None, None) => None,
+                }
+            }
+        };
+
+        match (value, fallback) {
+            (Some(value), _) if !is_empty(&value) => Ok(value),
+            (_, Some(fallback)) => self.resolve_string(fallback, location),
+            (Some(value), None) => Ok(value),
+            (None, None) => Err(TeraclioError::InvalidInput(format!(
+                "Unresolved reference '${{{name}}}' in '{}'",
+                describe(location)
+            ))),
+        }
+    }
+}
+
+/**
+ * Index of the `}` closing a reference, allowing nested `${...}` in fallbacks
+ * @author: skitsanos
+ */
+fn closing_brace(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(index),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+fn is_empty(value: &Value) -> bool {
+    value.is_null() || value.as_str() == Some("")
+}
+
+fn describe(location: &DataPath) -> String {
+    let location = location.to_string();
+    if location.is_empty() {
+        "(root)".to_string()
+    } else {
+        location
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::interpolate;
+    use serde_json::json;
+
+    #[test]
+    fn resolves_paths_with_types_and_fallbacks() {
+        let mut data = json!({
+            "base_url": "https://${host}",
+            "host": "example.com",
+            "api": "${base_url}/api",
+            "port": 8080,
+            "listen": "${port}",
+            "region": "${deploy.region:-eu-west-1}",
+            "literal": "$${not.a.ref}",
+            "home": "${env:TCL_TEST_UNSET_HOME:-/home/${host}}"
+        });
+        interpolate(&mut data).expect("interpolate");
+        assert_eq!(
+            data,
+            json!({
+                "base_url": "https://example.com",
+                "host": "example.com",
+                "api": "https://example.com/api",
+                "port": 8080,
+                "listen": 8080,
+                "region": "eu-west-1",
+                "literal": "${not.a.ref}",
+                "home": "/home/example.com"
+            })
+        );
+    }
+
+    #[test]
+    fn resolves_each_referenced_value_once() {
+        // Without caching, resolving v40 would visit v0 2^40 times
+        let mut data = json!({"v0": ""});
+        for level in 1..=40 {
+            let previous = level - 1;
+            data[format!("v{level}")] = json!(format!("${{v{previous}}}${{v{previous}}}"));
+        }
+        interpolate(&mut data).expect("interpolate");
+        assert_eq!(data["v40"], json!(""));
+    }
+
+    #[test]
+    fn reports_cycles_and_unresolved_references() {
+        let mut data = json!({"a": "${b}", "b": "x${a}"});
+        let err = interpolate(&mut data).expect_err("cycle");
+        assert!(
+            err.to_string().contains("Interpolation cycle: a -> b -> a"),
+            "{err}"
+        );
+
+        let mut data = json!({"db": {"url": "postgres://${db.host}"}});
+        let err = interpolate(&mut data).expect_err("unresolved");
+        assert!(
+            err.to_string()
+                .contains("Unresolved reference '${db.host}' in 'db.url'"),
+            "{err}"
+        );
+    }
+}
@@ -1,18 +1,49 @@
 use crate::cli::{generate_completions, Cli};
 use crate::engine::TemplateEngine;
+use crate::env::{env_data, EnvOptions};
 use crate::error::{Result, TeraclioError};
-use crate::utils::{parse_data_source, InputFormat};
-use clap::Parser;
+use crate::explain::{changes, explain, Layer};
+use crate::formats::csv::CsvOptions;
+use crate::formats::infer_scalar;
+use crate::formats::markdown::MarkdownOptions;
+use crate::formats::ndjson::NdjsonRecords;
+use crate::formats::sqlite::load_sqlite;
+use crate::formats::xml::XmlOptions;
+use crate::formats::yaml::{parse_yaml_documents, YamlDocuments};
+use crate::include::{included_files, resolve_refs};
+use crate::interpolate::interpolate;
+use crate::merge::{merge_json, merge_json_at, MergeOptions};
+use crate::patch::DataPatch;
+use crate::path::{DataPath, Segment};
+use crate::schema::DataSchema;
+use crate::source::{DocumentMode, SourceSpec};
+use crate::utils::{
+    declared_format, open_data_source, parse_data_source, parse_inline_data, parse_str,
+    InputFormat, ParseOptions,
+};
+use base64::{engine::general_purpose, Engine as _};
 use notify::{recommended_watcher, RecursiveMode, Watcher};
 use serde_json::Value;
+use std::collections::HashSet;
 use std::ffi::OsString;
-use std::path::Path;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 use std::sync::mpsc;
 
 mod cli;
 mod engine;
+mod env;
 mod error;
+mod explain;
 mod filters;
+mod formats;
+mod include;
+mod interpolate;
+mod merge;
+mod patch;
+mod path;
+mod schema;
+mod source;
 mod utils;
 
 const AVAILABLE_FILTERS: &[(&str, &str)] = &[
@@ -67,35 +98,201 @@ fn main() -> Result<()> {
 }
 
 /**
- * Deep-merge two JSON values. If both are objects, recursively merge keys.
- * Otherwise the overlay value wins.
+ * Build the parser options shared by all data sources from CLI args
  * @author: skitsanos
  */
-fn merge_json(base: &mut Value, overlay: Value) {
-    match (base, overlay) {
-        (Value::Object(base_map), Value::Object(overlay_map)) => {
-            for (key, value) in overlay_map {
-                let entry = base_map.entry(key).or_insert(Value::Null);
-                merge_json(entry, value);
-            }
+fn parse_options(args: &Cli) -> ParseOptions {
+    ParseOptions {
+        format: args.input_format,
+        csv: CsvOptions {
+            delimiter: args.csv_delimiter,
+            has_headers: !args.csv_no_header,
+            infer_types: args.csv_infer_types,
+        },
+        xml: XmlOptions {
+            force_arrays: args.xml_arrays.clone(),
+        },
+        markdown: MarkdownOptions {
+            render_html: args.markdown_html,
+        },
+        yaml_documents: YamlDocuments::Auto,
+        expand_dotted_keys: args.expand_dotted_keys,
+        keep_refs: args.keep_refs,
+        verbose: args.verbose,
+    }
+}
+
+/**
+ * Build the environment import options from CLI args
+ * @author: skitsanos
+ */
+fn env_options(args: &Cli) -> EnvOptions {
+    EnvOptions {
+        prefix: args.env_prefix.clone(),
+        keep_prefix: args.env_keep_prefix,
+        separator: args.env_separator.clone(),
+        lowercase: args.env_lowercase,
+        infer_types: args.env_infer_types,
+        allow: args.env_allow.clone(),
+        deny: args.env_deny.clone(),
+    }
+}
+
+/**
+ * Build the deep-merge options from CLI args
+ * @author: skitsanos
+ */
+fn merge_options(args: &Cli) -> MergeOptions {
+    MergeOptions {
+        arrays: args.merge_arrays,
+        paths: args.merge_paths.clone(),
+        merge_key: args.merge_key.clone(),
+        null_deletes: args.merge_null_deletes,
+    }
+}
+
+/**
+ * Parse and merge all data sources, apply patches, inject env vars and --set variables
+ * @author: skitsanos
+ */
+fn parse_data(args: &Cli) -> Result<Value> {
+    validate_stdin_source(args)?;
+    let mut json_data = load_sources(args, args.json_source.iter(), None)?;
+    finish_data(args, &DataSteps::load(args)?, &mut json_data, None)?;
+    Ok(json_data)
+}
+
+/**
+ * The patch and schema files used by the steps after the sources are merged,
+ * loaded once so batch mode can reuse them for every record
+ * @author: skitsanos
+ */
+struct DataSteps {
+    patches: Vec<DataPatch>,
+    schema: Option<DataSchema>,
+}
+
+impl DataSteps {
+    fn load(args: &Cli) -> Result<Self> {
+        Ok(Self {
+            patches: load_patches(args)?,
+            schema: load_schema(args)?,
+        })
+    }
+}
+
+/**
+ * Run the steps that follow merging the sources, in order: patches, environment
+ * and --set overrides, interpolation, then schema defaults and validation. When
+ * `layers` is given, the values supplied by patches and overrides are recorded
+ * in it for --explain-data.
+ * @author: skitsanos
+ */
+fn finish_data(
+    args: &Cli,
+    steps: &DataSteps,
+    json_data: &mut Value,
+    mut layers: Option<&mut Vec<Layer>>,
+) -> Result<()> {
+    for (patch, path) in steps.patches.iter().zip(&args.patches) {
+        let before = layers.is_some().then(|| json_data.clone());
+        patch.apply(json_data)?;
+        if let (Some(layers), Some(before)) = (layers.as_deref_mut(), before) {
+            layers.push(Layer {
+                origin: format!("--patch {path}"),
+                contents: None,
+                mount_depth: 0,
+                value: changes(&before, json_data),
+            });
         }
-        (base, overlay) => {
-            *base = overlay;
+    }
+
+    for item in apply_overrides(args, json_data)? {
+        if let Some(layers) = layers.as_deref_mut() {
+            let mut value = Value::Null;
+            item.path.set(&mut value, item.value)?;
+            layers.push(Layer {
+                origin: item.origin,
+                contents: None,
+                mount_depth: 0,
+                value,
+            });
         }
     }
+
+    if args.interpolate {
+        interpolate(json_data)?;
+    }
+    if let Some(schema) = &steps.schema {
+        enforce_schema(schema, args, json_data)?;
+    }
+    Ok(())
 }
 
 /**
- * Parse and merge all data sources, inject env vars and --set variables
+ * Load the data the same way as parse_data while recording each source and
+ * override as a layer, then describe where every value of the result came from
  * @author: skitsanos
  */
-fn parse_data(args: &Cli) -> Result<Value> {
-    // Validate that stdin source '-' appears at most once and is last
-    let stdin_positions: Vec<usize> = args
+fn explain_data(args: &Cli) -> Result<String> {
+    validate_stdin_source(args)?;
+    let mut layers = Vec::new();
+    let mut json_data = load_sources(args, args.json_source.iter(), Some(&mut layers))?;
+    finish_data(
+        args,
+        &DataSteps::load(args)?,
+        &mut json_data,
+        Some(&mut layers),
+    )?;
+    Ok(explain(&json_data, &layers))
+}
+
+/**
+ * Load the --patch files in the order they were given
+ * @author: skitsanos
+ */
+fn load_patches(args: &Cli) -> Result<Vec<DataPatch>> {
+    let options = parse_options(args);
+    args.patches
+        .iter()
+        .map(|path| DataPatch::load(path, &options))
+        .collect()
+}
+
+/**
+ * Load the --schema file, if any
+ * @author: skitsanos
+ */
+fn load_schema(args: &Cli) -> Result<Option<DataSchema>> {
+    args.schema.as_deref().map(DataSchema::load).transpose()
+}
+
+/**
+ * Apply schema defaults if requested, then validate the final data
+ * @author: skitsanos
+ */
+fn enforce_schema(schema: &DataSchema, args: &Cli, json_data: &mut Value) -> Result<()> {
+    if args.schema_defaults {
+        schema.apply_defaults(json_data);
+    }
+    schema.validate(json_data)
+}
+
+/**
+ * Validate that stdin source '-' appears at most once and is the last file
+ * source. Inline --data documents may still follow it.
+ * @author: skitsanos
+ */
+fn validate_stdin_source(args: &Cli) -> Result<()> {
+    let file_sources: Vec<&SourceSpec> = args
         .json_source
+        .iter()
+        .filter(|s| s.inline.is_none())
+        .collect();
+    let stdin_positions: Vec<usize> = file_sources
         .iter()
         .enumerate()
-        .filter(|(_, s)| s.as_str() == "-")
+        .filter(|(_, s)| s.is_stdin())
         .map(|(i, _)| i)
         .collect();
 
@@ -106,57 +303,359 @@ fn parse_data(args: &Cli) -> Result<Value> {
     }
 
     if let Some(&pos) = stdin_positions.first() {
-        if pos != args.json_source.len() - 1 {
+        if pos != file_sources.len() - 1 {
             return Err(TeraclioError::InvalidInput(
                 "Stdin source '-' must be the last source specified.".to_string(),
             ));
         }
     }
 
-    // Parse and deep-merge all data sources left to right
+    Ok(())
+}
+
+/**
+ * Parse and deep-merge the given data sources left to right. When `layers` is
+ * given, each mounted source is recorded in it for --explain-data.
+ * @author: skitsanos
+ */
+fn load_sources<'a>(
+    args: &Cli,
+    sources: impl Iterator<Item = &'a SourceSpec>,
+    mut layers: Option<&mut Vec<Layer>>,
+) -> Result<Value> {
+    let options = parse_options(args);
+    let merge = merge_options(args);
     let mut json_data = Value::Object(serde_json::Map::new());
-    for source in &args.json_source {
-        let data = parse_data_source(source, args.input_format)?;
-        merge_json(&mut json_data, data);
+    for source in sources.filter(|source| !skip_missing(args, source)) {
+        let data = source.mount_value(load_source(source, &options, &merge)?);
+        if let Some(layers) = layers.as_deref_mut() {
+            layers.push(source_layer(source, data.clone()));
+        }
+        merge_json(&mut json_data, data, &merge);
     }
+    Ok(json_data)
+}
 
-    // Add environment variables if requested
-    if args.include_env_vars {
-        if let Value::Object(ref mut map) = json_data {
-            let env_vars: serde_json::Map<String, Value> = std::env::vars()
-                .map(|(k, v)| (k, Value::String(v)))
-                .collect();
-            map.insert("env".to_string(), Value::Object(env_vars));
+/**
+ * Describe a loaded source for --explain-data. The file text is kept for line
+ * numbers, except for sources that are not a single file.
+ * @author: skitsanos
+ */
+fn source_layer(source: &SourceSpec, value: Value) -> Layer {
+    let contents = match &source.inline {
+        Some(inline) => Some(inline.text.clone()),
+        None if source.is_stdin() || source.sqlite || source.is_collection() => None,
+        None => std::fs::read_to_string(&source.path).ok(),
+    };
+    Layer {
+        origin: if source.is_stdin() {
+            "stdin".to_string()
         } else {
-            return Err(TeraclioError::InvalidInput(
-                "Cannot include environment variables: data source must be a JSON object when --env-vars is used."
-                    .to_string(),
-            ));
+            source.path.clone()
+        },
+        contents,
+        mount_depth: source
+            .mount
+            .as_ref()
+            .map_or(0, |mount| mount.split('.').count()),
+        value,
+    }
+}
+
+/**
+ * Whether to skip an optional source that does not exist, saying so unless --quiet
+ * @author: skitsanos
+ */
+fn skip_missing(args: &Cli, source: &SourceSpec) -> bool {
+    let skip = source.optional && source.is_missing();
+    if skip {
+        info(
+            args,
+            &format!(
+                "[teraclio] Optional source '{}' not found, skipping",
+                source.path
+            ),
+        );
+    }
+    skip
+}
+
+/**
+ * Replace each --source-lookup pattern with its levels, filling placeholders
+ * from the --set values
+ * @author: skitsanos
+ */
+fn expand_lookups(args: &mut Cli) -> Result<()> {
+    if !args.json_source.iter().any(|source| source.lookup) {
+        return Ok(());
+    }
+
+    let mut values = Value::Null;
+    for item in set_overrides(args)? {
+        item.path.set(&mut values, item.value)?;
+    }
+
+    let mut sources = Vec::new();
+    for source in std::mem::take(&mut args.json_source) {
+        if !source.lookup {
+            sources.push(source);
+            continue;
+        }
+        let (levels, unset) = source.expand_lookup(&values)?;
+        if let Some(name) = unset {
+            info(
+                args,
+                &format!(
+                    "[teraclio] Lookup '{}' stops at '{{{name}}}': no --set value",
+                    source.path
+                ),
+            );
         }
+        sources.extend(levels);
     }
+    args.json_source = sources;
+    Ok(())
+}
 
-    // Inject ad-hoc variables from --set key=value options
+/**
+ * Parse a single source. Glob and directory sources are deep-merged file by file
+ * in sorted order, or keyed by file stem when the source has a mount name.
+ * @author: skitsanos
+ */
+fn load_source(source: &SourceSpec, options: &ParseOptions, merge: &MergeOptions) -> Result<Value> {
+    let options = &source.parse_options(options);
+    if let Some(inline) = &source.inline {
+        return parse_inline_data(&inline.text, &source.path, inline.format, options);
+    }
+    if source.sqlite {
+        let data = load_sqlite(
+            &source.path,
+            source.query.as_deref(),
+            source.queries.as_deref(),
+        )?;
+        return source.select(data, &source.path);
+    }
+    if source.is_stdin() || !source.is_collection() {
+        let data = parse_data_source(&source.path, options)?;
+        return source.select(data, &source.path);
+    }
+
+    let files = source.collection_files()?;
+    if files
+        .iter()
+        .all(|file| InputFormat::detect_from_extension(file) == Some(InputFormat::Markdown))
+    {
+        return source.load_documents(&files, options);
+    }
+
+    let mut json_data = Value::Object(serde_json::Map::new());
+    for file in files {
+        let file_str = file.to_str().ok_or_else(|| {
+            TeraclioError::InvalidInput(format!("Path is not valid UTF-8: {}", file.display()))
+        })?;
+        let data = source.select(parse_data_source(file_str, options)?, file_str)?;
+
+        if source.mount.is_some() {
+            let stem = file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let mut keyed = serde_json::Map::new();
+            keyed.insert(stem, data);
+            merge_json(&mut json_data, Value::Object(keyed), merge);
+        } else {
+            merge_json(&mut json_data, data, merge);
+        }
+    }
+    Ok(json_data)
+}
+
+/**
+ * A value injected on top of the merged sources by --env-vars or a --set option
+ * @author: skitsanos
+ */
+struct Override {
+    /** The option that supplied the value, e.g. `--set db.port=5432` */
+    origin: String,
+    path: DataPath,
+    value: Value,
+}
+
+/**
+ * Collect the environment and --set values in the order they are applied:
+ * environment, --set, --set-string, --set-json, then --set-file
+ * @author: skitsanos
+ */
+fn overrides(args: &Cli) -> Result<Vec<Override>> {
+    let mut overrides = Vec::new();
+
+    // Add environment variables if requested
+    let env = env_options(args);
+    if args.include_env_vars || env.is_configured() {
+        overrides.push(Override {
+            origin: "environment".to_string(),
+            path: DataPath::default().child(Segment::Key("env".to_string())),
+            value: env_data(std::env::vars(), &env)?,
+        });
+    }
+
+    overrides.extend(set_overrides(args)?);
+    Ok(overrides)
+}
+
+/**
+ * Collect the ad-hoc variables from --set, --set-string, --set-json and
+ * --set-file options, in that order
+ * @author: skitsanos
+ */
+fn set_overrides(args: &Cli) -> Result<Vec<Override>> {
+    let mut overrides = Vec::new();
     for entry in &args.set_vars {
-        if let Some(pos) = entry.find('=') {
-            let key = &entry[..pos];
-            let value = &entry[pos + 1..];
-            if let Value::Object(ref mut map) = json_data {
-                map.insert(key.to_string(), Value::String(value.to_string()));
-            } else {
-                return Err(TeraclioError::InvalidInput(
-                    "Cannot set variables: data source must be a JSON object when --set is used."
-                        .to_string(),
-                ));
-            }
+        let (path, value) = split_assignment(entry, "--set")?;
+        overrides.push(Override {
+            origin: format!("--set {entry}"),
+            path,
+            value: infer_scalar(value),
+        });
+    }
+    for entry in &args.set_string_vars {
+        let (path, value) = split_assignment(entry, "--set-string")?;
+        overrides.push(Override {
+            origin: format!("--set-string {entry}"),
+            path,
+            value: Value::String(value.to_string()),
+        });
+    }
+    for entry in &args.set_json_vars {
+        let (path, value) = split_assignment(entry, "--set-json")?;
+        let value = serde_json::from_str(value).map_err(|e| {
+            TeraclioError::InvalidInput(format!("Invalid JSON in --set-json '{entry}': {e}"))
+        })?;
+        overrides.push(Override {
+            origin: format!("--set-json {entry}"),
+            path,
+            value,
+        });
+    }
+    for entry in &args.set_file_vars {
+        let (path, file) = split_assignment(entry, "--set-file")?;
+        overrides.push(Override {
+            origin: format!("--set-file {entry}"),
+            path,
+            value: read_file_value(file)?,
+        });
+    }
+
+    Ok(overrides)
+}
+
+/**
+ * Inject environment variables and --set variables on top of the merged sources,
+ * returning the overrides that were applied
+ * @author: skitsanos
+ */
+fn apply_overrides(args: &Cli, json_data: &mut Value) -> Result<Vec<Override>> {
+    let env_requested = args.include_env_vars || env_options(args).is_configured();
+    if env_requested && !json_data.is_object() {
+        return Err(TeraclioError::InvalidInput(
+            "Cannot include environment variables: data source must be a JSON object when --env-vars is used."
+                .to_string(),
+        ));
+    }
+
+    let merge = merge_options(args);
+    let overrides = overrides(args)?;
+    for item in &overrides {
+        let mut value = item.value.clone();
+        // An object deep-merges with the object the sources left at the path
+        if let (Some(existing @ Value::Object(_)), Value::Object(_)) =
+            (item.path.get(json_data), &value)
+        {
+            let mut merged = existing.clone();
+            merge_json_at(&mut merged, value, &merge, &item.path.dotted_keys());
+            value = merged;
+        }
+        item.path.set(json_data, value)?;
+    }
+    Ok(overrides)
+}
+
+/**
+ * Split a KEY=VALUE option into its parsed data path and raw value
+ * @author: skitsanos
+ */
+fn split_assignment<'a>(entry: &'a str, option: &str) -> Result<(DataPath, &'a str)> {
+    let (key, value) = entry.split_once('=').ok_or_else(|| {
+        TeraclioError::InvalidInput(format!(
+            "Invalid {option} format '{entry}': expected KEY=VALUE"
+        ))
+    })?;
+    Ok((DataPath::parse(key)?, value))
+}
+
+/**
+ * Read a --set-file file as a string value: its text when it is valid UTF-8,
+ * base64 (standard alphabet, padded) otherwise
+ * @author: skitsanos
+ */
+fn read_file_value(file: &str) -> Result<Value> {
+    let bytes = std::fs::read(file).map_err(|e| {
+        TeraclioError::InvalidInput(format!("Cannot read --set-file file '{file}': {e}"))
+    })?;
+    Ok(Value::String(match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => general_purpose::STANDARD.encode(e.into_bytes()),
+    }))
+}
+
+/**
+ * Register the files included by the data sources, SQLite queries files, schema
+ * and patches that are not watched yet. NDJSON sources are not read. Files that
+ * fail to parse are skipped; the render reports them.
+ * @author: skitsanos
+ */
+fn watch_includes(
+    args: &Cli,
+    watcher: &mut impl Watcher,
+    watched: &mut HashSet<PathBuf>,
+) -> Result<()> {
+    if args.keep_refs {
+        return Ok(());
+    }
+
+    let options = parse_options(args);
+    let mut files: Vec<PathBuf> = Vec::new();
+    for source in &args.json_source {
+        if let Some(queries) = &source.queries {
+            files.push(PathBuf::from(queries));
+        }
+        if source.inline.is_some() || source.is_stdin() || source.sqlite || source.is_missing() {
+            continue;
+        }
+        if source.is_collection() {
+            files.extend(source.collection_files().unwrap_or_default());
         } else {
-            return Err(TeraclioError::InvalidInput(format!(
-                "Invalid --set format '{}': expected KEY=VALUE",
-                entry
-            )));
+            files.push(PathBuf::from(&source.path));
         }
     }
+    files.extend(args.schema.iter().chain(&args.patches).map(PathBuf::from));
 
-    Ok(json_data)
+    for file in files {
+        let Some(file) = file.to_str() else {
+            continue;
+        };
+        // NDJSON records carry no includes worth watching, and reading a large
+        // batch source here would undo its streaming
+        if declared_format(file, args.input_format).ok().flatten() == Some(InputFormat::Ndjson) {
+            continue;
+        }
+        for included in included_files(file, &options).unwrap_or_default() {
+            if watched.insert(included.clone()) {
+                watcher.watch(&included, RecursiveMode::NonRecursive)?;
+            }
+        }
+    }
+    Ok(())
 }
 
 /**
@@ -180,6 +679,11 @@ fn validate_output(content: &str, format: InputFormat) -> Result<()> {
                 TeraclioError::InvalidInput(format!("Output is not valid TOML: {e}"))
             })?;
         }
+        other => {
+            parse_str(content, other, &ParseOptions::default()).map_err(|e| {
+                TeraclioError::InvalidInput(format!("Output is not valid {}: {e}", other.label()))
+            })?;
+        }
     }
     Ok(())
 }
@@ -331,10 +835,174 @@ fn process_directory(
     Ok(())
 }
 
+/**
+ * Render the template once per record of the batch source (an NDJSON file or a
+ * source with `?docs=each`). The record is merged over the remaining sources.
+ * NDJSON is streamed line by line, so the batch file is never held in memory as a whole.
+ * @author: skitsanos
+ */
+fn run_batch_mode(template_path: &Path, args: &Cli) -> Result<()> {
+    if template_path.is_dir() {
+        return Err(TeraclioError::InvalidInput(
+            "--batch requires a template file, not a directory".to_string(),
+        ));
+    }
+
+    validate_stdin_source(args)?;
+    let batch_index = find_batch_source(args)?;
+    let base = load_sources(
+        args,
+        args.json_source
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != batch_index)
+            .map(|(_, source)| source),
+        None,
+    )?;
+
+    let dest_pattern = match &args.output_file {
+        Some(dest) => {
+            let pattern = dest.to_str().ok_or_else(|| {
+                TeraclioError::InvalidInput("Destination path is not valid UTF-8.".to_string())
+            })?;
+            if !pattern.contains("{{") && !pattern.contains("{%") {
+                return Err(TeraclioError::InvalidInput(
+                    "--dest must be a path pattern such as 'out/{{ data.id }}.txt' when using --batch"
+                        .to_string(),
+                ));
+            }
+            Some(pattern)
+        }
+        None => None,
+    };
+
+    let mut engine = TemplateEngine::new(args.strict);
+    engine.load_template(template_path)?;
+
+    let mut count = 0;
+    let merge = merge_options(args);
+    let steps = DataSteps::load(args)?;
+    let batch_source = &args.json_source[batch_index];
+    for record in batch_records(batch_source, args)? {
+        let mut json_data = base.clone();
+        merge_json(&mut json_data, batch_source.mount_value(record?), &merge);
+        finish_data(args, &steps, &mut json_data, None)?;
+
+        let rendered = engine.render(template_path, &json_data)?;
+        let output_path = match dest_pattern {
+            Some(pattern) => Some(PathBuf::from(engine.render_str(pattern, &json_data)?)),
+            None => None,
+        };
+
+        if let Some(parent) = output_path.as_deref().and_then(Path::parent) {
+            if !args.check && !args.diff && !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+
+        handle_rendered_output(&rendered, output_path.as_deref(), args)?;
+        count += 1;
+    }
+
+    info(args, &format!("[teraclio] Rendered {count} records"));
+    Ok(())
+}
+
+/**
+ * Find the index of the single source that drives batch mode. Sources marked
+ * `?docs=each` take precedence; otherwise the NDJSON source is used.
+ * @author: skitsanos
+ */
+fn find_batch_source(args: &Cli) -> Result<usize> {
+    let mut batch_sources: Vec<usize> = args
+        .json_source
+        .iter()
+        .enumerate()
+        .filter(|(_, source)| source.documents == Some(DocumentMode::Each))
+        .map(|(index, _)| index)
+        .collect();
+
+    if batch_sources.is_empty() {
+        for (index, source) in args.json_source.iter().enumerate() {
+            if source.is_collection() || source.sqlite || source.inline.is_some() {
+                continue;
+            }
+            if source.optional && source.is_missing() {
+                continue;
+            }
+            if declared_format(&source.path, args.input_format)? == Some(InputFormat::Ndjson) {
+                batch_sources.push(index);
+            }
+        }
+    }
+
+    match batch_sources.as_slice() {
+        [index] => Ok(*index),
+        [] => Err(TeraclioError::InvalidInput(
+            "--batch requires an NDJSON source (.ndjson, .jsonl, or --format ndjson) or a source with '?docs=each'".to_string(),
+        )),
+        _ => Err(TeraclioError::InvalidInput(
+            "Batch mode supports only one NDJSON or '?docs=each' source".to_string(),
+        )),
+    }
+}
+
+/**
+ * Iterate over the records of a batch source: NDJSON lines are streamed,
+ * YAML documents are parsed up front. Sources without a declared format
+ * (stdin, no extension) are read as YAML streams. References are resolved
+ * and the source selector is applied to each record.
+ * @author: skitsanos
+ */
+fn batch_records(
+    source: &SourceSpec,
+    args: &Cli,
+) -> Result<Box<dyn Iterator<Item = Result<Value>>>> {
+    let mut reader = open_data_source(&source.path)?;
+    let records: Box<dyn Iterator<Item = Result<Value>>> =
+        match declared_format(&source.path, args.input_format)? {
+            Some(InputFormat::Ndjson) => Box::new(NdjsonRecords::new(reader)),
+            Some(InputFormat::Yaml) | None => {
+                let mut contents = String::new();
+                reader.read_to_string(&mut contents)?;
+                let documents = parse_yaml_documents(&contents)?;
+                Box::new(documents.into_iter().map(Ok))
+            }
+            _ => {
+                return Err(TeraclioError::InvalidInput(format!(
+                    "Batch source '{}' must be NDJSON or YAML",
+                    source.path
+                )))
+            }
+        };
+
+    if source.selector.is_none() && args.keep_refs {
+        return Ok(records);
+    }
+    let source = source.clone();
+    let options = parse_options(args);
+    Ok(Box::new(records.map(move |record| {
+        let record = if options.keep_refs {
+            record?
+        } else {
+            resolve_refs(record?, &source.path, &options)?
+        };
+        source.select(record, &source.path)
+    })))
+}
+
 fn execute(args: &Cli) -> Result<()> {
     let template_path_os = require_template_path(args)?;
     let template_path = Path::new(template_path_os);
 
+    let per_document = args
+        .json_source
+        .iter()
+        .any(|source| source.documents == Some(DocumentMode::Each));
+    if args.batch || per_document {
+        return run_batch_mode(template_path, args);
+    }
+
     if template_path.is_dir() {
         let json_data = parse_data(args)?;
         return run_directory_mode(template_path, args, &json_data);
@@ -368,7 +1036,7 @@ fn info(args: &Cli, msg: &str) {
  * @author: skitsanos
  */
 fn run() -> Result<()> {
-    let args = Cli::parse();
+    let mut args = Cli::parse_args();
 
     // Generate shell completions and exit
     if let Some(shell) = args.completions {
@@ -390,6 +1058,21 @@ fn run() -> Result<()> {
         return Ok(());
     }
 
+    expand_lookups(&mut args)?;
+
+    // Print where each data value came from and exit
+    if args.explain_data {
+        print!("{}", explain_data(&args)?);
+        return Ok(());
+    }
+
+    // Print the render context and exit
+    if let Some(format) = args.dump_context {
+        let json_data = parse_data(&args)?;
+        print!("{}", TemplateEngine::dump_context(&json_data, format)?);
+        return Ok(());
+    }
+
     let template_path_os = require_template_path(&args)?;
     let template_path = Path::new(template_path_os);
 
@@ -401,7 +1084,7 @@ fn run() -> Result<()> {
     // If watch mode is enabled, enter the watch loop
     if args.watch {
         for source in &args.json_source {
-            if source == "-" {
+            if source.is_stdin() {
                 return Err(TeraclioError::InvalidInput(
                     "Watch mode cannot be used with stdin ('-') as source.".to_string(),
                 ));
@@ -429,9 +1112,38 @@ fn run() -> Result<()> {
             let watch_root = template_path.parent().unwrap_or_else(|| Path::new("."));
             watcher.watch(watch_root, RecursiveMode::NonRecursive)?;
         }
-        for source in &args.json_source {
-            watcher.watch(Path::new(source.as_str()), RecursiveMode::NonRecursive)?;
+        for source in args.json_source.iter().filter(|s| s.inline.is_none()) {
+            let path = source.watch_path();
+            if path.exists() {
+                watcher.watch(&path, RecursiveMode::NonRecursive)?;
+            } else {
+                // A missing optional source is picked up when it is created
+                let parent = match path.parent() {
+                    Some(parent) if !parent.as_os_str().is_empty() => parent,
+                    _ => Path::new("."),
+                };
+                if parent.is_dir() {
+                    watcher.watch(parent, RecursiveMode::NonRecursive)?;
+                }
+            }
+        }
+        for patch in &args.patches {
+            watcher.watch(Path::new(patch), RecursiveMode::NonRecursive)?;
+        }
+        if let Some(schema) = &args.schema {
+            watcher.watch(Path::new(schema), RecursiveMode::NonRecursive)?;
+        }
+        for queries in args.json_source.iter().filter_map(|s| s.queries.as_ref()) {
+            watcher.watch(Path::new(queries), RecursiveMode::NonRecursive)?;
+        }
+        for entry in &args.set_file_vars {
+            if let Some((_, file)) = entry.split_once('=') {
+                watcher.watch(Path::new(file), RecursiveMode::NonRecursive)?;
+            }
         }
+        // Files pulled in by $ref or !include; new ones are added after each render
+        let mut watched_includes = HashSet::new();
+        watch_includes(&args, &mut watcher, &mut watched_includes)?;
 
         loop {
             match rx.recv() {
@@ -441,6 +1153,7 @@ fn run() -> Result<()> {
                         if let Err(e) = execute(&args) {
                             eprintln!("[teraclio] Re-render error: {e}");
                         }
+                        watch_includes(&args, &mut watcher, &mut watched_includes)?;
                     }
                 }
                 Ok(Err(e)) => {
@@ -0,0 +1,220 @@
+use clap::ValueEnum;
+use serde_json::Value;
+
+/**
+ * How arrays are combined when an overlay provides the same key as the base
+ * @author: skitsanos
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ArrayMerge {
+    /** The overlay array replaces the base array */
+    #[default]
+    Replace,
+    /** Overlay items are added after the base items */
+    Append,
+    /** Overlay items are added before the base items */
+    Prepend,
+    /** Overlay items are appended unless an equal item already exists */
+    Unique,
+    /** Object items with the same merge key are deep-merged, others are appended */
+    MergeByKey,
+}
+
+/**
+ * Array merge strategy applied to one dotted path, parsed from `PATH=STRATEGY[:KEY]`
+ * @author: skitsanos
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathStrategy {
+    pub path: String,
+    pub strategy: ArrayMerge,
+    pub key: Option<String>,
+}
+
+impl PathStrategy {
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let (path, rest) = raw
+            .split_once('=')
+            .ok_or_else(|| format!("expected PATH=STRATEGY[:KEY], got '{raw}'"))?;
+        let (strategy, key) = match rest.split_once(':') {
+            Some((strategy, key)) => (strategy, Some(key.to_string())),
+            None => (rest, None),
+        };
+        let strategy = ArrayMerge::from_str(strategy, true)?;
+        if key.is_some() && strategy != ArrayMerge::MergeByKey {
+            return Err(format!(
+                "a key can only be given for merge-by-key, got '{rest}'"
+            ));
+        }
+
+        Ok(Self {
+            path: path.to_string(),
+            strategy,
+            key,
+        })
+    }
+}
+
+/**
+ * Options controlling how layered data sources are deep-merged
+ * @author: skitsanos
+ */
+#[derive(Clone, Debug)]
+pub struct MergeOptions {
+    pub arrays: ArrayMerge,
+    pub paths: Vec<PathStrategy>,
+    pub merge_key: String,
+    pub null_deletes: bool,
+}
+
+impl Default for MergeOptions {
+    fn default() -> Self {
+        Self {
+            arrays: ArrayMerge::Replace,
+            paths: Vec::new(),
+            merge_key: "name".to_string(),
+            null_deletes: false,
+        }
+    }
+}
+
+impl MergeOptions {
+    fn strategy_for(&self, path: &str) -> (ArrayMerge, &str) {
+        match self.paths.iter().rev().find(|p| p.path == path) {
+            Some(p) => (p.strategy, p.key.as_deref().unwrap_or(&self.merge_key)),
+            None => (self.arrays, &self.merge_key),
+        }
+    }
+}
+
+/**
+ * Deep-merge two JSON values. If both are objects, recursively merge keys.
+ * Arrays are combined according to the strategy for their dotted path.
+ * Otherwise the overlay value wins.
+ * @author: skitsanos
+ */
+pub fn merge_json(base: &mut Value, overlay: Value, options: &MergeOptions) {
+    merge_at(base, overlay, options, "");
+}
+
+/**
+ * Deep-merge an overlay into the value found at `path` of the merged data, so
+ * per-path array strategies apply as if the overlay had been merged at the root
+ * @author: skitsanos
+ */
+pub fn merge_json_at(base: &mut Value, overlay: Value, options: &MergeOptions, path: &str) {
+    merge_at(base, overlay, options, path);
+}
+
+fn merge_at(base: &mut Value, overlay: Value, options: &MergeOptions, path: &str) {
+    match (base, overlay) {
+        (Value::Object(base_map), Value::Object(overlay_map)) => {
+            for (key, value) in overlay_map {
+                if value.is_null() && options.null_deletes {
+                    base_map.remove(&key);
+                    continue;
+                }
+
+                let child_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                let entry = base_map.entry(key).or_insert(Value::Null);
+                merge_at(entry, value, options, &child_path);
+            }
+        }
+        (Value::Array(base_items), Value::Array(overlay_items)) => {
+            let (strategy, merge_key) = options.strategy_for(path);
+            match strategy {
+                ArrayMerge::Replace => *base_items = overlay_items,
+                ArrayMerge::Append => base_items.extend(overlay_items),
+                ArrayMerge::Prepend => {
+                    let mut items = overlay_items;
+                    items.append(base_items);
+                    *base_items = items;
+                }
+                ArrayMerge::Unique => {
+                    for item in overlay_items {
+                        if !base_items.contains(&item) {
+                            base_items.push(item);
+                        }
+                    }
+                }
+                ArrayMerge::MergeByKey => {
+                    for item in overlay_items {
+                        let existing = item.get(merge_key).and_then(|id| {
+                            base_items
+                                .iter_mut()
+                                .find(|candidate| candidate.get(merge_key) == Some(id))
+                        });
+                        match existing {
+                            Some(target) => merge_at(target, item, options, path),
+                            None => base_items.push(item),
+                        }
+                    }
+                }
+            }
+        }
+        (base, overlay) => {
+            *base = overlay;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_json, ArrayMerge, MergeOptions, PathStrategy};
+    use serde_json::json;
+
+    #[test]
+    fn replaces_arrays_by_default() {
+        let mut base = json!({"ports": [80], "name": "a"});
+        merge_json(&mut base, json!({"ports": [443]}), &MergeOptions::default());
+        assert_eq!(base, json!({"ports": [443], "name": "a"}));
+    }
+
+    #[test]
+    fn applies_global_and_per_path_strategies() {
+        let options = MergeOptions {
+            arrays: ArrayMerge::Append,
+            paths: vec![PathStrategy::parse("tags=unique").expect("parse")],
+            ..MergeOptions::default()
+        };
+        let mut base = json!({"ports": [80], "tags": ["a", "b"]});
+        merge_json(
+            &mut base,
+            json!({"ports": [443], "tags": ["b", "c"]}),
+            &options,
+        );
+        assert_eq!(base, json!({"ports": [80, 443], "tags": ["a", "b", "c"]}));
+    }
+
+    #[test]
+    fn merges_items_by_key_and_deletes_null_tombstones() {
+        let options = MergeOptions {
+            paths: vec![PathStrategy::parse("middlewares=merge-by-key:id").expect("parse")],
+            null_deletes: true,
+            ..MergeOptions::default()
+        };
+        let mut base = json!({
+            "debug": true,
+            "middlewares": [{"id": "auth", "level": 1}, {"id": "gzip"}]
+        });
+        merge_json(
+            &mut base,
+            json!({"debug": null, "middlewares": [{"id": "auth", "level": 2}, {"id": "cors"}]}),
+            &options,
+        );
+        assert_eq!(
+            base,
+            json!({"middlewares": [{"id": "auth", "level": 2}, {"id": "gzip"}, {"id": "cors"}]})
+        );
+    }
+
+    #[test]
+    fn rejects_key_for_other_strategies() {
+        assert!(PathStrategy::parse("ports=append:name").is_err());
+        assert!(PathStrategy::parse("ports").is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use crate::error::{Result, TeraclioError};
+use crate::utils::{parse_data_source, ParseOptions};
+use json_patch::PatchOperation;
+use serde_json::Value;
+
+/**
+ * A patch applied to the merged data after all sources. An array is an RFC 6902
+ * JSON Patch (a list of operations); an object is an RFC 7386 JSON Merge Patch.
+ * The patch file can be written in any supported input format.
+ * @author: skitsanos
+ */
+pub struct DataPatch {
+    path: String,
+    kind: PatchKind,
+}
+
+enum PatchKind {
+    /** Operations with the `op` name each was written with, for error messages */
+    Json(Vec<(String, PatchOperation)>),
+    Merge(Value),
+}
+
+impl DataPatch {
+    pub fn load(path: &str, options: &ParseOptions) -> Result<Self> {
+        let options = ParseOptions {
+            format: None,
+            ..options.clone()
+        };
+        let kind = match parse_data_source(path, &options)? {
+            Value::Array(operations) => PatchKind::Json(
+                operations
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, operation)| {
+                        let name = operation
+                            .get("op")
+                            .and_then(Value::as_str)
+                            .unwrap_or_default()
+                            .to_string();
+                        serde_json::from_value(operation)
+                            .map(|operation| (name, operation))
+                            .map_err(|e| {
+                                TeraclioError::InvalidInput(format!(
+                                    "Invalid JSON Patch '{path}': operation {index}: {e}"
+                                ))
+                            })
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+            ),
+            Value::Object(map) => PatchKind::Merge(Value::Object(map)),
+            _ => {
+                return Err(TeraclioError::InvalidInput(format!(
+                    "Patch '{path}' must be a JSON Patch array or a JSON Merge Patch object"
+                )))
+            }
+        };
+        Ok(Self {
+            path: path.to_string(),
+            kind,
+        })
+    }
+
+    /**
+     * Apply the patch to the data. A JSON Patch is all-or-nothing: when an
+     * operation fails (including a failed `test`), the data is left unchanged
+     * and the error names the operation's index, name and path.
+     * @author: skitsanos
+     */
+    pub fn apply(&self, data: &mut Value) -> Result<()> {
+        match &self.kind {
+            PatchKind::Merge(patch) => {
+                json_patch::merge(data, patch);
+                Ok(())
+            }
+            PatchKind::Json(operations) => {
+                let patch: Vec<PatchOperation> = operations
+                    .iter()
+                    .map(|(_, operation)| operation.clone())
+                    .collect();
+                json_patch::patch(data, &patch).map_err(|e| {
+                    let name = operations
+                        .get(e.operation)
+                        .map_or("", |(name, _)| name.as_str());
+                    let path = e.path.as_str();
+                    let path = if path.is_empty() { "/" } else { path };
+                    TeraclioError::InvalidInput(format!(
+                        "JSON Patch '{}' failed at operation {} ({name} {path}): {}",
+                        self.path, e.operation, e.kind
+                    ))
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DataPatch;
+    use crate::utils::test_support::write_temp_file;
+    use crate::utils::ParseOptions;
+    use serde_json::json;
+
+    fn load(name: &str, contents: &str) -> DataPatch {
+        let path = write_temp_file(name, contents);
+        let patch = DataPatch::load(path.to_str().expect("utf8"), &ParseOptions::default());
+        std::fs::remove_file(&path).expect("cleanup");
+        patch.expect("load patch")
+    }
+
+    #[test]
+    fn applies_json_patch_and_merge_patch() {
+        let mut data = json!({"items": ["a", "b", "c", "d"], "db": {"host": "x", "port": 1}});
+
+        load(
+            "ops.yaml",
+            "- {op: test, path: /db/host, value: x}\n- {op: remove, path: /items/0}\n- {op: replace, path: /items/2, value: D}\n",
+        )
+        .apply(&mut data)
+        .expect("json patch");
+        load("merge.json", r#"{"db": {"port": null, "user": "app"}}"#)
+            .apply(&mut data)
+            .expect("merge patch");
+
+        assert_eq!(
+            data,
+            json!({"items": ["b", "c", "D"], "db": {"host": "x", "user": "app"}})
+        );
+    }
+
+    #[test]
+    fn reports_failed_operation_and_leaves_data_unchanged() {
+        let mut data = json!({"items": [1, 2]});
+        let patch = load(
+            "fail.json",
+            r#"[{"op": "add", "path": "/items/-", "value": 3}, {"op": "remove", "path": "/items/7"}]"#,
+        );
+
+        let err = patch.apply(&mut data).expect_err("error").to_string();
+        assert!(
+            err.contains("failed at operation 1 (remove /items/7)"),
+            "{err}"
+        );
+        assert_eq!(data, json!({"items": [1, 2]}));
+    }
+}
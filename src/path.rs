@@ -0,0 +1,290 @@
+use crate::error::{Result, TeraclioError};
+use serde_json::{Map, Value};
+use std::fmt;
+
+/**
+ * Most nulls `set` adds to reach an index past the end of an array
+ * @author: skitsanos
+ */
+const MAX_ARRAY_PADDING: usize = 1024;
+
+/**
+ * One step of a data path: an object key or an array index
+ * @author: skitsanos
+ */
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/**
+ * A dotted data path with array indices, e.g. `servers[0].host`.
+ * A backslash escapes the next character, so `a\.b` is the single key `a.b`.
+ * The default (empty) path is the data root.
+ * @author: skitsanos
+ */
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DataPath {
+    segments: Vec<Segment>,
+}
+
+impl DataPath {
+    /**
+     * Parse a path expression. A leading `.` is allowed (`.services.api`).
+     * @author: skitsanos
+     */
+    pub fn parse(raw: &str) -> Result<Self> {
+        let invalid = |reason: &str| {
+            TeraclioError::InvalidInput(format!("Invalid data path '{raw}': {reason}"))
+        };
+
+        let mut segments = Vec::new();
+        let mut key = String::new();
+        let mut chars = raw.strip_prefix('.').unwrap_or(raw).chars().peekable();
+        // Whether the previous token closed an index, so an empty key is expected
+        let mut after_index = false;
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    key.push(chars.next().ok_or_else(|| invalid("trailing escape"))?);
+                }
+                '.' => {
+                    if key.is_empty() && !after_index {
+                        return Err(invalid("empty key"));
+                    }
+                    if !key.is_empty() {
+                        segments.push(Segment::Key(std::mem::take(&mut key)));
+                    }
+                    after_index = false;
+                    if chars.peek().is_none() {
+                        return Err(invalid("trailing '.'"));
+                    }
+                }
+                '[' => {
+                    if !key.is_empty() {
+                        segments.push(Segment::Key(std::mem::take(&mut key)));
+                    }
+                    let mut digits = String::new();
+                    let mut closed = false;
+                    for c in chars.by_ref() {
+                        if c == ']' {
+                            closed = true;
+                            break;
+                        }
+                        digits.push(c);
+                    }
+                    if !closed {
+                        return Err(invalid("unterminated '['"));
+                    }
+                    let index = digits
+                        .parse::<usize>()
+                        .map_err(|_| invalid("array index must be a non-negative integer"))?;
+                    segments.push(Segment::Index(index));
+                    after_index = true;
+                }
+                _ => {
+                    if after_index {
+                        return Err(invalid("expected '.' or '[' after an index"));
+                    }
+                    key.push(c);
+                }
+            }
+        }
+
+        if !key.is_empty() {
+            segments.push(Segment::Key(key));
+        }
+        if segments.is_empty() {
+            return Err(invalid("empty path"));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /**
+     * The segments of this path, outermost first
+     * @author: skitsanos
+     */
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /**
+     * The key segments joined with '.', the form --merge-path strategies are
+     * matched against. Array indices are left out.
+     * @author: skitsanos
+     */
+    pub fn dotted_keys(&self) -> String {
+        self.segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Key(key) => Some(key.as_str()),
+                Segment::Index(_) => None,
+            })
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    /**
+     * The path one step below this one
+     * @author: skitsanos
+     */
+    pub fn child(&self, segment: Segment) -> Self {
+        let mut segments = self.segments.clone();
+        segments.push(segment);
+        Self { segments }
+    }
+
+    /**
+     * Look up the value at this path
+     * @author: skitsanos
+     */
+    pub fn get<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        self.segments
+            .iter()
+            .try_fold(value, |current, segment| match segment {
+                Segment::Key(key) => current.get(key),
+                Segment::Index(index) => current.get(index),
+            })
+    }
+
+    /**
+     * Set the value at this path, creating missing objects and arrays on the way.
+     * Arrays are padded with nulls when the index is past the end. Existing
+     * scalars are never silently replaced by containers.
+     * @author: skitsanos
+     */
+    pub fn set(&self, target: &mut Value, value: Value) -> Result<()> {
+        let mut current = target;
+        for (position, segment) in self.segments.iter().enumerate() {
+            if current.is_null() {
+                *current = match segment {
+                    Segment::Key(_) => Value::Object(Map::new()),
+                    Segment::Index(_) => Value::Array(Vec::new()),
+                };
+            }
+
+            current = match (segment, current) {
+                (Segment::Key(key), Value::Object(map)) => {
+                    map.entry(key.clone()).or_insert(Value::Null)
+                }
+                (Segment::Index(index), Value::Array(items)) => {
+                    if index.saturating_sub(items.len()) > MAX_ARRAY_PADDING {
+                        return Err(TeraclioError::InvalidInput(format!(
+                            "Cannot set '{self}': index {index} is more than {MAX_ARRAY_PADDING} past the end of an array of {} item(s)",
+                            items.len()
+                        )));
+                    }
+                    if items.len() <= *index {
+                        items.resize(index + 1, Value::Null);
+                    }
+                    &mut items[*index]
+                }
+                (segment, _) => {
+                    let parent = DataPath {
+                        segments: self.segments[..position].to_vec(),
+                    };
+                    let expected = match segment {
+                        Segment::Key(_) => "an object",
+                        Segment::Index(_) => "an array",
+                    };
+                    let location = if position == 0 {
+                        "the data root".to_string()
+                    } else {
+                        format!("'{parent}'")
+                    };
+                    return Err(TeraclioError::InvalidInput(format!(
+                        "Cannot set '{self}': {location} is not {expected}"
+                    )));
+                }
+            };
+        }
+
+        *current = value;
+        Ok(())
+    }
+}
+
+impl fmt::Display for DataPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (position, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Key(key) => {
+                    if position > 0 {
+                        write!(f, ".")?;
+                    }
+                    write!(f, "{}", key.replace('.', "\\."))?;
+                }
+                Segment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DataPath, Segment};
+    use serde_json::json;
+
+    #[test]
+    fn parses_keys_indices_and_escapes() {
+        let path = DataPath::parse("servers[0].tls\\.cert").expect("parse");
+        assert_eq!(
+            path.segments,
+            vec![
+                Segment::Key("servers".to_string()),
+                Segment::Index(0),
+                Segment::Key("tls.cert".to_string()),
+            ]
+        );
+        assert_eq!(path.to_string(), "servers[0].tls\\.cert");
+
+        assert!(DataPath::parse("a..b").is_err());
+        assert!(DataPath::parse("a[x]").is_err());
+        let err = DataPath::parse("b[0").expect_err("unterminated");
+        assert!(err.to_string().contains("unterminated '['"), "{err}");
+        assert!(DataPath::parse("").is_err());
+    }
+
+    #[test]
+    fn sets_nested_values_and_pads_arrays() {
+        let mut data = json!({"db": {"port": 5432}});
+        DataPath::parse("db.host")
+            .expect("parse")
+            .set(&mut data, json!("x"))
+            .expect("set");
+        DataPath::parse("servers[1].host")
+            .expect("parse")
+            .set(&mut data, json!("b"))
+            .expect("set");
+        assert_eq!(
+            data,
+            json!({"db": {"port": 5432, "host": "x"}, "servers": [null, {"host": "b"}]})
+        );
+        assert_eq!(
+            DataPath::parse("servers[1].host")
+                .expect("parse")
+                .get(&data),
+            Some(&json!("b"))
+        );
+
+        let err = DataPath::parse("servers[18446744073709551615]")
+            .expect("parse")
+            .set(&mut data, json!(1))
+            .expect_err("index too far");
+        assert!(err.to_string().contains("past the end"), "{err}");
+    }
+
+    #[test]
+    fn refuses_to_descend_into_scalars() {
+        let mut data = json!({"db": "postgres://"});
+        let err = DataPath::parse("db.host")
+            .expect("parse")
+            .set(&mut data, json!("x"))
+            .expect_err("error");
+        assert!(err.to_string().contains("'db' is not an object"), "{err}");
+    }
+}
@@ -0,0 +1,174 @@
+use crate::error::{Result, TeraclioError};
+use crate::utils::{parse_data_source, ParseOptions};
+use jsonschema::Validator;
+use serde_json::Value;
+
+/**
+ * Maximum number of local `$ref` hops followed while applying defaults
+ * @author: skitsanos
+ */
+const MAX_REF_DEPTH: usize = 32;
+
+/**
+ * A JSON Schema (draft 2020-12) that the merged data must satisfy before rendering.
+ * The schema file can be written in any supported input format.
+ * @author: skitsanos
+ */
+pub struct DataSchema {
+    path: String,
+    schema: Value,
+    validator: Validator,
+}
+
+impl DataSchema {
+    pub fn load(path: &str) -> Result<Self> {
+        let schema = parse_data_source(path, &ParseOptions::default())?;
+        let validator = jsonschema::draft202012::new(&schema).map_err(|e| {
+            TeraclioError::InvalidInput(format!("Invalid JSON Schema '{path}': {e}"))
+        })?;
+        Ok(Self {
+            path: path.to_string(),
+            schema,
+            validator,
+        })
+    }
+
+    /**
+     * Check the data against the schema, reporting every violation on its own
+     * line as `<JSON pointer>: <message>`
+     * @author: skitsanos
+     */
+    pub fn validate(&self, data: &Value) -> Result<()> {
+        let violations: Vec<String> = self
+            .validator
+            .iter_errors(data)
+            .map(|error| {
+                let location = error.instance_path().to_string();
+                let location = if location.is_empty() {
+                    "/".to_string()
+                } else {
+                    location
+                };
+                format!("  {location}: {error}")
+            })
+            .collect();
+
+        if violations.is_empty() {
+            return Ok(());
+        }
+        Err(TeraclioError::InvalidInput(format!(
+            "Data does not match schema '{}' ({} violation{}):\n{}",
+            self.path,
+            violations.len(),
+            if violations.len() == 1 { "" } else { "s" },
+            violations.join("\n")
+        )))
+    }
+
+    /**
+     * Fill in missing object keys from `default` values in `properties`. Nested
+     * objects, array `items` and `allOf` branches are followed, as are local
+     * `$ref`s (`#/$defs/...`). Existing values are never changed.
+     * @author: skitsanos
+     */
+    pub fn apply_defaults(&self, data: &mut Value) {
+        apply_defaults_at(&self.schema, &self.schema, data, 0);
+    }
+}
+
+fn apply_defaults_at(root: &Value, schema: &Value, data: &mut Value, depth: usize) {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        if depth < MAX_REF_DEPTH {
+            if let Some(target) = reference.strip_prefix('#').and_then(|p| root.pointer(p)) {
+                apply_defaults_at(root, target, data, depth + 1);
+            }
+        }
+    }
+
+    if let Some(branches) = schema.get("allOf").and_then(Value::as_array) {
+        for branch in branches {
+            apply_defaults_at(root, branch, data, depth);
+        }
+    }
+
+    match data {
+        Value::Object(map) => {
+            let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+                return;
+            };
+            for (key, property) in properties {
+                if !map.contains_key(key) {
+                    match property.get("default") {
+                        Some(default) => {
+                            map.insert(key.clone(), default.clone());
+                        }
+                        None => continue,
+                    }
+                }
+                if let Some(child) = map.get_mut(key) {
+                    apply_defaults_at(root, property, child, depth);
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items").filter(|s| s.is_object()) {
+                for item in items {
+                    apply_defaults_at(root, item_schema, item, depth);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply_defaults_at, DataSchema};
+    use crate::utils::test_support::write_temp_file;
+    use serde_json::json;
+
+    #[test]
+    fn reports_every_violation_with_its_path() {
+        let schema = json!({
+            "type": "object",
+            "required": ["name"],
+            "properties": {"db": {"properties": {"port": {"type": "integer"}}}}
+        });
+        let path = write_temp_file("json", &schema.to_string());
+        let schema = DataSchema::load(path.to_str().expect("utf8")).expect("load");
+        let err = schema
+            .validate(&json!({"db": {"port": "x"}}))
+            .expect_err("error")
+            .to_string();
+        std::fs::remove_file(path).expect("cleanup");
+
+        assert!(err.contains("(2 violations)"), "{err}");
+        assert!(err.contains("  /db/port: "), "{err}");
+        assert!(
+            err.contains("  /: \"name\" is a required property"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn fills_missing_keys_from_defaults() {
+        let schema = json!({
+            "$defs": {"server": {"properties": {"tls": {"default": false}}}},
+            "properties": {
+                "db": {"default": {}, "properties": {"port": {"default": 5432}}},
+                "servers": {"items": {"$ref": "#/$defs/server"}},
+                "name": {"default": "app"}
+            }
+        });
+        let mut data = json!({"name": "api", "servers": [{"host": "a"}, {"tls": true}]});
+        apply_defaults_at(&schema, &schema, &mut data, 0);
+        assert_eq!(
+            data,
+            json!({
+                "name": "api",
+                "db": {"port": 5432},
+                "servers": [{"host": "a", "tls": false}, {"tls": true}]
+            })
+        );
+    }
+}
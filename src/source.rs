@@ -0,0 +1,673 @@
+use crate::error::{Result, TeraclioError};
+use crate::formats::markdown::document_list;
+use crate::formats::yaml::YamlDocuments;
+use crate::path::DataPath;
+use crate::utils::{parse_data_source, InputFormat, ParseOptions};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/**
+ * How a multi-document source (YAML stream or NDJSON) is consumed
+ * @author: skitsanos
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DocumentMode {
+    /** All documents become an array, even if there is only one */
+    Array,
+    /** The template is rendered once per document */
+    Each,
+}
+
+/**
+ * Selects the sub-tree of a source that is merged into the data: a JSON Pointer
+ * (`#/services/api`) or a dotted path (`#services.api`, `#.servers[0]`)
+ * @author: skitsanos
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Selector {
+    Pointer(String),
+    Path(DataPath),
+}
+
+impl Selector {
+    pub fn parse(raw: &str) -> std::result::Result<Self, String> {
+        if raw.is_empty() || raw.starts_with('/') {
+            Ok(Self::Pointer(raw.to_string()))
+        } else {
+            DataPath::parse(raw)
+                .map(Self::Path)
+                .map_err(|e| e.to_string())
+        }
+    }
+
+    pub fn select<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        match self {
+            Self::Pointer(pointer) => value.pointer(pointer),
+            Self::Path(path) => path.get(value),
+        }
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Pointer(pointer) => write!(f, "#{pointer}"),
+            Self::Path(path) => write!(f, "#{path}"),
+        }
+    }
+}
+
+/**
+ * A data source given on the command line, optionally mounted under a key:
+ * `path`, `-`, `name=path` or `name=-`. Dotted names mount into nested objects.
+ * Per-source options follow the path as `?key=value&key=value`, and a
+ * `#selector` at the very end picks a sub-tree of the parsed data.
+ * `sqlite:file.db?query=SQL` reads the result of a query from a database.
+ * A leading `?` marks the source as optional: it is skipped when missing.
+ * @author: skitsanos
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceSpec {
+    pub path: String,
+    pub mount: Option<String>,
+    pub documents: Option<DocumentMode>,
+    pub selector: Option<Selector>,
+    pub sqlite: bool,
+    /** SQL query whose rows become the source data (SQLite sources) */
+    pub query: Option<String>,
+    /** File mapping names to SQL queries (SQLite sources) */
+    pub queries: Option<String>,
+    /** Field that orders the documents of a Markdown collection (`-field` for descending) */
+    pub sort: Option<String>,
+    /** Document given on the command line instead of a file (--data, --data-yaml) */
+    pub inline: Option<InlineData>,
+    /** Skip the source when its file does not exist */
+    pub optional: bool,
+    /** The path is a --source-lookup pattern with `{name}` placeholders */
+    pub lookup: bool,
+}
+
+/**
+ * The text of a --data or --data-yaml option. `path` of its source is the
+ * option name, so messages about the source name the option.
+ * @author: skitsanos
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InlineData {
+    pub text: String,
+    /** Format forced by the option (YAML for --data-yaml); otherwise --format or detection */
+    pub format: Option<InputFormat>,
+}
+
+impl SourceSpec {
+    /**
+     * Parse a --source value. A leading `name=` is only treated as a mount when the
+     * name is a valid identifier path and the whole value is not an existing file.
+     * @author: skitsanos
+     */
+    pub fn parse(raw: &str) -> std::result::Result<Self, String> {
+        let (raw, optional) = match raw.strip_prefix('?') {
+            Some(rest) if !Path::new(raw).exists() => (rest, true),
+            _ => (raw, false),
+        };
+        let (raw, selector) = split_selector(raw);
+        let (location, options) = split_options(raw);
+
+        let mut spec = Self {
+            path: location.to_string(),
+            mount: None,
+            documents: None,
+            selector: selector.map(Selector::parse).transpose()?,
+            sqlite: false,
+            query: None,
+            queries: None,
+            sort: None,
+            inline: None,
+            optional,
+            lookup: false,
+        };
+        if let Some((name, path)) = location.split_once('=') {
+            if is_mount_name(name) && !Path::new(location).exists() {
+                if path.is_empty() {
+                    return Err(format!("missing path after '{name}='"));
+                }
+                spec.path = path.to_string();
+                spec.mount = Some(name.to_string());
+            }
+        }
+
+        if let Some(database) = spec.path.strip_prefix("sqlite:") {
+            if database.is_empty() || database == "-" {
+                return Err("sqlite: sources need a database file".to_string());
+            }
+            spec.path = database.to_string();
+            spec.sqlite = true;
+        }
+
+        for option in options.into_iter().flat_map(|options| options.split('&')) {
+            let (key, value) = option
+                .split_once('=')
+                .ok_or_else(|| format!("expected key=value source option, got '{option}'"))?;
+            spec.set_option(key, value)?;
+        }
+
+        match (spec.sqlite, spec.query.is_some(), spec.queries.is_some()) {
+            (true, false, false) => {
+                Err("sqlite: sources need a ?query=SQL or ?queries=FILE option".to_string())
+            }
+            (true, true, true) => Err("use either query= or queries=, not both".to_string()),
+            (false, true, _) | (false, _, true) => {
+                Err("query= and queries= only apply to sqlite: sources".to_string())
+            }
+            _ => Ok(spec),
+        }
+    }
+
+    fn set_option(&mut self, key: &str, value: &str) -> std::result::Result<(), String> {
+        match key {
+            "docs" => {
+                self.documents = Some(match value {
+                    "array" => DocumentMode::Array,
+                    "each" => DocumentMode::Each,
+                    _ => return Err(format!("docs must be 'array' or 'each', got '{value}'")),
+                });
+            }
+            "query" => self.query = Some(value.to_string()),
+            "queries" => self.queries = Some(value.to_string()),
+            "sort" => self.sort = Some(value.to_string()),
+            _ => return Err(format!("unknown source option '{key}'")),
+        }
+        Ok(())
+    }
+
+    /**
+     * Parser options for this source: the shared options with per-source overrides applied
+     * @author: skitsanos
+     */
+    pub fn parse_options(&self, shared: &ParseOptions) -> ParseOptions {
+        let mut options = shared.clone();
+        if self.documents == Some(DocumentMode::Array) {
+            options.yaml_documents = YamlDocuments::Array;
+        }
+        options
+    }
+
+    /**
+     * Apply the selector, if any, to data parsed from `file`. A selector that does
+     * not resolve is an error naming the file and the selector.
+     * @author: skitsanos
+     */
+    pub fn select(&self, value: Value, file: &str) -> Result<Value> {
+        let Some(selector) = &self.selector else {
+            return Ok(value);
+        };
+        let file = if file == "-" { "stdin" } else { file };
+        selector.select(&value).cloned().ok_or_else(|| {
+            TeraclioError::InvalidInput(format!(
+                "Selector '{selector}' did not match anything in '{file}'"
+            ))
+        })
+    }
+
+    /**
+     * A source for a document given inline with `option` (--data or --data-yaml)
+     * @author: skitsanos
+     */
+    pub fn inline(option: &str, text: &str, format: Option<InputFormat>) -> Self {
+        Self {
+            path: option.to_string(),
+            mount: None,
+            documents: None,
+            selector: None,
+            sqlite: false,
+            query: None,
+            queries: None,
+            sort: None,
+            inline: Some(InlineData {
+                text: text.to_string(),
+                format,
+            }),
+            optional: false,
+            lookup: false,
+        }
+    }
+
+    /**
+     * Parse a --source-lookup value: a source whose path contains `{name}`
+     * placeholders, e.g. `config/{env}/{host}.yaml`
+     * @author: skitsanos
+     */
+    pub fn parse_lookup(raw: &str) -> std::result::Result<Self, String> {
+        let mut spec = Self::parse(raw)?;
+        if spec.inline.is_some() || spec.sqlite || spec.is_stdin() {
+            return Err("lookups only apply to file sources".to_string());
+        }
+        if !spec.path.contains('{') {
+            return Err(format!(
+                "lookup pattern '{}' has no {{name}} placeholder",
+                spec.path
+            ));
+        }
+        spec.lookup = true;
+        spec.optional = true;
+        Ok(spec)
+    }
+
+    /**
+     * Expand a lookup source into one optional source per level, least specific
+     * first. Each placeholder opens a level named after the path before it, so
+     * `config/{env}/{host}.yaml` yields `config.yaml`, `config/{env}.yaml` and
+     * `config/{env}/{host}.yaml`. Placeholders are dotted paths looked up in
+     * `values`; the expansion stops at the first level whose placeholder has no
+     * value, and that placeholder is returned alongside the levels.
+     * @author: skitsanos
+     */
+    pub fn expand_lookup(&self, values: &Value) -> Result<(Vec<Self>, Option<String>)> {
+        let mut levels = Vec::new();
+        for pattern in lookup_levels(&self.path) {
+            let mut path = String::new();
+            let mut rest = pattern.as_str();
+            while let Some(start) = rest.find('{') {
+                let end = rest[start..]
+                    .find('}')
+                    .map(|end| start + end)
+                    .ok_or_else(|| {
+                        TeraclioError::InvalidInput(format!(
+                            "Unterminated '{{' in lookup pattern '{}'",
+                            self.path
+                        ))
+                    })?;
+                let name = &rest[start + 1..end];
+                let value = match DataPath::parse(name)?.get(values) {
+                    None | Some(Value::Null) => return Ok((levels, Some(name.to_string()))),
+                    Some(Value::String(text)) => text.clone(),
+                    Some(value @ (Value::Number(_) | Value::Bool(_))) => value.to_string(),
+                    Some(_) => {
+                        return Err(TeraclioError::InvalidInput(format!(
+                            "Lookup placeholder '{{{name}}}' in '{}' must be a scalar value",
+                            self.path
+                        )))
+                    }
+                };
+                path.push_str(&rest[..start]);
+                path.push_str(&value);
+                rest = &rest[end + 1..];
+            }
+            path.push_str(rest);
+
+            levels.push(Self {
+                path,
+                lookup: false,
+                ..self.clone()
+            });
+        }
+        Ok((levels, None))
+    }
+
+    /**
+     * Whether the source has nothing to read: a file that does not exist or a
+     * glob pattern without matches. Stdin and inline documents are never missing.
+     * @author: skitsanos
+     */
+    pub fn is_missing(&self) -> bool {
+        if self.inline.is_some() || self.is_stdin() || Path::new(&self.path).exists() {
+            return false;
+        }
+        !is_glob_pattern(&self.path)
+            || glob::glob(&self.path).is_ok_and(|mut paths| paths.next().is_none())
+    }
+
+    pub fn is_stdin(&self) -> bool {
+        self.inline.is_none() && self.path == "-"
+    }
+
+    /**
+     * Whether the source names several files: a glob pattern or a directory.
+     * An existing file whose name contains glob characters is read as-is.
+     * @author: skitsanos
+     */
+    pub fn is_collection(&self) -> bool {
+        if self.inline.is_some() {
+            return false;
+        }
+        let path = Path::new(&self.path);
+        path.is_dir() || (is_glob_pattern(&self.path) && !path.is_file())
+    }
+
+    /**
+     * List the files of a glob or directory source in sorted order. Directories are
+     * read non-recursively and only files with a recognised extension are included.
+     * @author: skitsanos
+     */
+    pub fn collection_files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = if is_glob_pattern(&self.path) {
+            glob::glob(&self.path)
+                .map_err(|e| {
+                    TeraclioError::InvalidInput(format!(
+                        "Invalid glob pattern '{}': {e}",
+                        self.path
+                    ))
+                })?
+                .filter_map(std::result::Result::ok)
+                .filter(|path| path.is_file())
+                .collect::<Vec<_>>()
+        } else {
+            let mut files = Vec::new();
+            for entry in std::fs::read_dir(&self.path)? {
+                let path = entry?.path();
+                let hidden = path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_none_or(|name| name.starts_with('.'));
+                if path.is_file()
+                    && !hidden
+                    && path.extension().is_some()
+                    && InputFormat::detect_from_extension(&path).is_some()
+                {
+                    files.push(path);
+                }
+            }
+            files
+        };
+
+        if files.is_empty() {
+            return Err(TeraclioError::InvalidInput(format!(
+                "No data source files matched '{}'",
+                self.path
+            )));
+        }
+
+        files.sort();
+        Ok(files)
+    }
+
+    /**
+     * Read the Markdown files of a collection source as an array of documents,
+     * each with this source's selector applied, in the order of its `sort` option
+     * @author: skitsanos
+     */
+    pub fn load_documents(&self, files: &[PathBuf], options: &ParseOptions) -> Result<Value> {
+        let mut documents = Vec::new();
+        for file in files {
+            let file_str = file.to_str().ok_or_else(|| {
+                TeraclioError::InvalidInput(format!("Path is not valid UTF-8: {}", file.display()))
+            })?;
+            let document = self.select(parse_data_source(file_str, options)?, file_str)?;
+            let stem = file
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            documents.push((stem, document));
+        }
+        Ok(document_list(documents, self.sort.as_deref()))
+    }
+
+    /**
+     * Path to register with the file watcher: the directory for glob patterns
+     * and directories, the file itself otherwise
+     * @author: skitsanos
+     */
+    pub fn watch_path(&self) -> PathBuf {
+        if !is_glob_pattern(&self.path) {
+            return PathBuf::from(&self.path);
+        }
+
+        let mut base = PathBuf::new();
+        for component in Path::new(&self.path).components() {
+            if is_glob_pattern(&component.as_os_str().to_string_lossy()) {
+                break;
+            }
+            base.push(component);
+        }
+        if base.as_os_str().is_empty() {
+            PathBuf::from(".")
+        } else {
+            base
+        }
+    }
+
+    /**
+     * Place a parsed value under the mount key, if any
+     * @author: skitsanos
+     */
+    pub fn mount_value(&self, value: Value) -> Value {
+        match &self.mount {
+            Some(name) => name.rsplit('.').fold(value, |inner, segment| {
+                let mut map = Map::new();
+                map.insert(segment.to_string(), inner);
+                Value::Object(map)
+            }),
+            None => value,
+        }
+    }
+}
+
+/**
+ * The level patterns of a lookup pattern, least specific first: the path before
+ * each placeholder (without a trailing separator) plus the file extension,
+ * then the full pattern
+ * @author: skitsanos
+ */
+fn lookup_levels(pattern: &str) -> Vec<String> {
+    let extension = Path::new(pattern)
+        .extension()
+        .map(|extension| extension.to_string_lossy().into_owned())
+        .filter(|extension| !extension.contains('}'));
+
+    let mut levels: Vec<String> = Vec::new();
+    if let Some(extension) = extension {
+        for (index, _) in pattern.match_indices('{') {
+            let prefix = pattern[..index].trim_end_matches(['/', '\\', '.', '-', '_']);
+            let level = format!("{prefix}.{extension}");
+            if !prefix.is_empty() && !levels.contains(&level) {
+                levels.push(level);
+            }
+        }
+    }
+    levels.push(pattern.to_string());
+    levels
+}
+
+/**
+ * Split a trailing `#selector` off a source value, unless the whole value names
+ * an existing file
+ * @author: skitsanos
+ */
+fn split_selector(raw: &str) -> (&str, Option<&str>) {
+    if Path::new(raw).exists() {
+        return (raw, None);
+    }
+    match raw.rsplit_once('#') {
+        Some((location, selector)) if !location.is_empty() => (location, Some(selector)),
+        _ => (raw, None),
+    }
+}
+
+/**
+ * Split `path?key=value&...` into the path and its options. A '?' only starts the
+ * options when it is followed by `name=`, so glob wildcards keep working.
+ * @author: skitsanos
+ */
+fn split_options(raw: &str) -> (&str, Option<&str>) {
+    if Path::new(raw).exists() {
+        return (raw, None);
+    }
+
+    for (index, _) in raw.match_indices('?') {
+        let rest = &raw[index + 1..];
+        let name_len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        if name_len > 0 && rest[name_len..].starts_with('=') {
+            return (&raw[..index], Some(rest));
+        }
+    }
+    (raw, None)
+}
+
+fn is_glob_pattern(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+fn is_mount_name(name: &str) -> bool {
+    name.split('.').all(|segment| {
+        let mut chars = segment.chars();
+        matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DocumentMode, Selector, SourceSpec};
+    use serde_json::json;
+
+    #[test]
+    fn parses_mounted_sources() {
+        let spec = SourceSpec::parse("users=data/users.json").expect("parse");
+        assert_eq!(spec.mount.as_deref(), Some("users"));
+        assert_eq!(spec.path, "data/users.json");
+
+        let stdin = SourceSpec::parse("input=-").expect("parse");
+        assert!(stdin.is_stdin());
+
+        let plain = SourceSpec::parse("./a=b.json").expect("parse");
+        assert_eq!(plain.mount, None);
+    }
+
+    #[test]
+    fn parses_source_options() {
+        let spec = SourceSpec::parse("k8s=manifests/?.yaml?docs=each").expect("parse");
+        assert_eq!(spec.mount.as_deref(), Some("k8s"));
+        assert_eq!(spec.path, "manifests/?.yaml");
+        assert_eq!(spec.documents, Some(DocumentMode::Each));
+
+        assert!(SourceSpec::parse("a.yaml?docs=all").is_err());
+        assert!(SourceSpec::parse("a.yaml?color=red").is_err());
+    }
+
+    #[test]
+    fn watches_the_directory_of_glob_patterns() {
+        let spec = SourceSpec::parse("services=config/services/*.yaml").expect("parse");
+        assert!(spec.is_collection());
+        assert_eq!(
+            spec.watch_path(),
+            std::path::PathBuf::from("config/services")
+        );
+    }
+
+    #[test]
+    fn mounts_under_nested_names() {
+        let spec = SourceSpec::parse("db.primary=db.json").expect("parse");
+        assert_eq!(
+            spec.mount_value(json!({"host": "x"})),
+            json!({"db": {"primary": {"host": "x"}}})
+        );
+    }
+
+    #[test]
+    fn selects_sub_trees_by_pointer_or_path() {
+        let data = json!({"services": {"api": {"port": 80}, "a/b": 1}});
+
+        let spec = SourceSpec::parse("api=big.json?docs=array#/services/api").expect("parse");
+        assert_eq!(spec.path, "big.json");
+        assert_eq!(spec.mount.as_deref(), Some("api"));
+        assert_eq!(spec.documents, Some(DocumentMode::Array));
+        assert!(matches!(spec.selector, Some(Selector::Pointer(_))));
+        assert_eq!(
+            spec.select(data.clone(), "big.json").expect("select"),
+            json!({"port": 80})
+        );
+
+        let spec = SourceSpec::parse("big.json#.services.api.port").expect("parse");
+        assert_eq!(
+            spec.select(data.clone(), "big.json").expect("select"),
+            json!(80)
+        );
+
+        let spec = SourceSpec::parse("big.json#/services/a~1b").expect("parse");
+        assert_eq!(
+            spec.select(data.clone(), "big.json").expect("select"),
+            json!(1)
+        );
+
+        let err = SourceSpec::parse("big.json#/services/web")
+            .expect("parse")
+            .select(data, "big.json")
+            .expect_err("error");
+        assert_eq!(
+            err.to_string(),
+            "Invalid input: Selector '#/services/web' did not match anything in 'big.json'"
+        );
+    }
+
+    #[test]
+    fn parses_sqlite_sources() {
+        let spec =
+            SourceSpec::parse("hosts=sqlite:inventory.db?query=SELECT * FROM hosts WHERE up = 1")
+                .expect("parse");
+        assert!(spec.sqlite);
+        assert_eq!(spec.path, "inventory.db");
+        assert_eq!(spec.mount.as_deref(), Some("hosts"));
+        assert_eq!(
+            spec.query.as_deref(),
+            Some("SELECT * FROM hosts WHERE up = 1")
+        );
+
+        assert!(SourceSpec::parse("sqlite:inventory.db").is_err());
+        assert!(SourceSpec::parse("data.json?query=SELECT 1").is_err());
+    }
+
+    #[test]
+    fn parses_optional_sources() {
+        let spec = SourceSpec::parse("?local=local-override.yaml#db").expect("parse");
+        assert!(spec.optional);
+        assert_eq!(spec.path, "local-override.yaml");
+        assert_eq!(spec.mount.as_deref(), Some("local"));
+        assert!(spec.is_missing());
+
+        let plain = SourceSpec::parse("Cargo.toml").expect("parse");
+        assert!(!plain.optional);
+        assert!(!plain.is_missing());
+    }
+
+    #[test]
+    fn expands_lookup_levels_until_a_placeholder_is_unset() {
+        let spec = SourceSpec::parse_lookup("cfg=config/{env}/{host}.yaml").expect("parse");
+        let paths = |values| {
+            let (levels, unset) = spec.expand_lookup(&values).expect("expand");
+            let paths: Vec<String> = levels.into_iter().map(|level| level.path).collect();
+            (paths, unset)
+        };
+
+        assert_eq!(
+            paths(json!({"env": "prod", "host": "web1"})),
+            (
+                vec![
+                    "config.yaml".to_string(),
+                    "config/prod.yaml".to_string(),
+                    "config/prod/web1.yaml".to_string()
+                ],
+                None
+            )
+        );
+        assert_eq!(
+            paths(json!({"env": "prod"})),
+            (
+                vec!["config.yaml".to_string(), "config/prod.yaml".to_string()],
+                Some("host".to_string())
+            )
+        );
+
+        let (levels, _) = SourceSpec::parse_lookup("values-{deploy.env}.yaml")
+            .expect("parse")
+            .expand_lookup(&json!({"deploy": {"env": 2}}))
+            .expect("expand");
+        assert_eq!(levels[0].path, "values.yaml");
+        assert_eq!(levels[1].path, "values-2.yaml");
+        assert!(levels.iter().all(|level| level.optional && !level.lookup));
+
+        assert!(SourceSpec::parse_lookup("config.yaml").is_err());
+    }
+}
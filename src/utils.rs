@@ -1,33 +1,144 @@
 use crate::error::{Result, TeraclioError};
+use crate::formats::csv::{parse_delimited, CsvOptions};
+use crate::formats::dotenv::parse_dotenv;
+use crate::formats::ini::parse_ini;
+use crate::formats::json5::parse_json5;
+use crate::formats::markdown::{parse_markdown, MarkdownOptions};
+use crate::formats::ndjson::parse_ndjson;
+use crate::formats::properties::parse_properties;
+use crate::formats::toml::parse_toml;
+use crate::formats::xml::{parse_xml, XmlOptions};
+use crate::formats::yaml::{parse_yaml, YamlDocuments};
+use crate::include::resolve_refs;
 use clap::ValueEnum;
 use serde_json::Value;
 use std::fs;
-use std::io::Read;
+use std::io::{BufRead, BufReader, Read};
 use std::path::{Path, PathBuf};
 
-#[derive(Clone, Copy, Debug, ValueEnum)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
 pub enum InputFormat {
     Json,
+    #[value(name = "json5", alias = "jsonc")]
+    Json5,
     #[value(name = "yaml", alias = "yml")]
     Yaml,
     Toml,
+    Csv,
+    Tsv,
+    Xml,
+    #[value(name = "dotenv", alias = "env")]
+    Dotenv,
+    Ini,
+    Properties,
+    #[value(name = "ndjson", alias = "jsonl")]
+    Ndjson,
+    #[value(name = "markdown", alias = "md")]
+    Markdown,
 }
 
 impl InputFormat {
+    /**
+     * Human-readable format name used in messages
+     * @author: skitsanos
+     */
+    pub fn label(&self) -> &'static str {
+        match self {
+            InputFormat::Json => "JSON",
+            InputFormat::Json5 => "JSON5",
+            InputFormat::Yaml => "YAML",
+            InputFormat::Toml => "TOML",
+            InputFormat::Csv => "CSV",
+            InputFormat::Tsv => "TSV",
+            InputFormat::Xml => "XML",
+            InputFormat::Dotenv => "dotenv",
+            InputFormat::Ini => "INI",
+            InputFormat::Properties => "properties",
+            InputFormat::Ndjson => "NDJSON",
+            InputFormat::Markdown => "Markdown",
+        }
+    }
+
     pub fn detect_from_extension(path: &Path) -> Option<Self> {
-        if let Some(extension) = path.extension().and_then(|ext| ext.to_str()) {
-            match extension.to_lowercase().as_str() {
-                "yaml" | "yml" => Some(InputFormat::Yaml),
-                "toml" => Some(InputFormat::Toml),
-                "json" => Some(InputFormat::Json),
-                _ => None,
+        let by_extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::from_extension);
+        if by_extension.is_some() {
+            return by_extension;
+        }
+
+        // ".env" and ".env.local" style names have no recognised extension
+        let file_name = path.file_name().and_then(|name| name.to_str())?;
+        (file_name == ".env" || file_name.starts_with(".env.")).then_some(InputFormat::Dotenv)
+    }
+
+    fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_lowercase().as_str() {
+            "yaml" | "yml" => Some(InputFormat::Yaml),
+            "toml" => Some(InputFormat::Toml),
+            "json" => Some(InputFormat::Json),
+            "json5" | "jsonc" => Some(InputFormat::Json5),
+            "csv" => Some(InputFormat::Csv),
+            "tsv" | "tab" => Some(InputFormat::Tsv),
+            "xml" => Some(InputFormat::Xml),
+            "env" => Some(InputFormat::Dotenv),
+            "ini" => Some(InputFormat::Ini),
+            "properties" => Some(InputFormat::Properties),
+            "ndjson" | "jsonl" => Some(InputFormat::Ndjson),
+            "md" | "markdown" => Some(InputFormat::Markdown),
+            _ => None,
+        }
+    }
+
+    /**
+     * Guess the format of data from its content, trying JSON, NDJSON, TOML and
+     * then YAML. YAML only counts when it yields a mapping or a sequence, since
+     * almost any text is a valid YAML scalar.
+     * @author: skitsanos
+     */
+    pub fn detect_from_content(contents: &str) -> Option<Self> {
+        let trimmed = contents.trim_start();
+        if trimmed.starts_with('{') || trimmed.starts_with('[') {
+            if serde_json::from_str::<Value>(contents).is_ok() {
+                return Some(InputFormat::Json);
             }
-        } else {
-            Some(InputFormat::Json)
+            let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+            if lines.all(|line| serde_json::from_str::<Value>(line).is_ok()) {
+                return Some(InputFormat::Ndjson);
+            }
+        }
+
+        if toml::from_str::<toml::Table>(contents).is_ok() {
+            return Some(InputFormat::Toml);
+        }
+
+        match serde_yaml::from_str::<serde_yaml::Value>(contents) {
+            Ok(serde_yaml::Value::Mapping(_) | serde_yaml::Value::Sequence(_)) => {
+                Some(InputFormat::Yaml)
+            }
+            _ => None,
         }
     }
 }
 
+/**
+ * Options that influence how data sources are read and parsed
+ * @author: skitsanos
+ */
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+    pub format: Option<InputFormat>,
+    pub csv: CsvOptions,
+    pub xml: XmlOptions,
+    pub markdown: MarkdownOptions,
+    pub yaml_documents: YamlDocuments,
+    pub expand_dotted_keys: bool,
+    /** Leave `$ref` objects and `!include` tags unresolved */
+    pub keep_refs: bool,
+    pub verbose: bool,
+}
+
 /**
  * Parse data source from a file path or stdin ("-") into a serde_json::Value
  * @author: skitsanos
@@ -35,48 +146,30 @@ impl InputFormat {
  * # Arguments
  *
  * * `source` - Data source path or "-" for stdin
- * * `format` - Optional format specification, auto-detected for files if omitted
+ * * `options` - Format override (auto-detected for files if omitted) and parser settings
  *
  * # Returns
  *
  * A serde_json::Value containing the parsed data
  */
-pub fn parse_data_source(source: &str, format: Option<InputFormat>) -> Result<Value> {
-    let (contents, input_format) = if source == "-" {
-        let mut input = String::new();
-        let mut stdin = std::io::stdin();
-        stdin.read_to_string(&mut input)?;
-
-        let input_format = format.ok_or_else(|| {
-            TeraclioError::InvalidInput(
-                "When reading from stdin, --format must be specified (json, yaml, or toml)."
-                    .to_string(),
-            )
-        })?;
-
-        (input, input_format)
-    } else {
-        let source_path = PathBuf::from(source);
-        if !source_path.exists() {
-            return Err(TeraclioError::InvalidInput(format!(
-                "Data source file does not exist: {}",
-                source_path.display()
-            )));
-        }
+pub fn parse_data_source(source: &str, options: &ParseOptions) -> Result<Value> {
+    let value = read_data_source(source, options)?;
+    if options.keep_refs {
+        return Ok(value);
+    }
+    resolve_refs(value, source, options)
+}
 
-        let contents = fs::read_to_string(&source_path)?;
-        let input_format = format
-            .or_else(|| InputFormat::detect_from_extension(&source_path))
-            .ok_or_else(|| {
-                TeraclioError::InvalidInput(format!(
-                    "Unsupported input format for file '{}'. Supported formats: json, yaml, toml. \
-                     Provide --format explicitly.",
-                    source_path.display()
-                ))
-            })?;
-
-        (contents, input_format)
-    };
+/**
+ * Parse a data source without resolving `$ref` objects and `!include` tags
+ * @author: skitsanos
+ */
+pub fn read_data_source(source: &str, options: &ParseOptions) -> Result<Value> {
+    let mut reader = open_data_source(source)?;
+    let declared = declared_format(source, options.format)?;
+
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
 
     if contents.trim().is_empty() {
         return Err(TeraclioError::InvalidInput(
@@ -84,47 +177,188 @@ pub fn parse_data_source(source: &str, format: Option<InputFormat>) -> Result<Va
         ));
     }
 
-    let value = match input_format {
-        InputFormat::Json => serde_json::from_str(&contents).map_err(TeraclioError::JsonError)?,
-        InputFormat::Yaml => serde_yaml::from_str(&contents)
-            .map_err(|e| TeraclioError::InvalidInput(format!("YAML parsing error: {e}")))?,
-        InputFormat::Toml => toml::from_str(&contents)
-            .map_err(|e| TeraclioError::InvalidInput(format!("TOML parsing error: {e}")))?,
+    let input_format = match declared {
+        Some(format) => format,
+        None => detect_format(&contents, display_source(source), options)?,
+    };
+
+    parse_str(&contents, input_format, options)
+}
+
+/**
+ * Parse a document given on the command line (--data, --data-yaml). Without an
+ * explicit format the format is detected from the content, like stdin.
+ * References are resolved relative to the working directory.
+ * @author: skitsanos
+ */
+pub fn parse_inline_data(
+    contents: &str,
+    option: &str,
+    format: Option<InputFormat>,
+    options: &ParseOptions,
+) -> Result<Value> {
+    if contents.trim().is_empty() {
+        return Err(TeraclioError::InvalidInput(format!(
+            "{option} value is empty"
+        )));
+    }
+
+    let input_format = match format.or(options.format) {
+        Some(format) => format,
+        None => detect_format(contents, option, options)?,
+    };
+    let value = parse_str(contents, input_format, options)?;
+    if options.keep_refs {
+        return Ok(value);
+    }
+    resolve_refs(value, "-", options)
+}
+
+fn detect_format(contents: &str, source: &str, options: &ParseOptions) -> Result<InputFormat> {
+    let format = InputFormat::detect_from_content(contents).ok_or_else(|| {
+        TeraclioError::InvalidInput(format!(
+            "Could not detect the format of '{source}' from its content (tried JSON, TOML and YAML). \
+             Provide --format explicitly."
+        ))
+    })?;
+    if options.verbose {
+        eprintln!("[teraclio] Detected {} content in {source}", format.label());
+    }
+    Ok(format)
+}
+
+/**
+ * Determine the format of a data source from the explicit override or the file extension.
+ * Returns None for stdin and extension-less files, whose format is detected from content.
+ * @author: skitsanos
+ */
+pub fn declared_format(source: &str, format: Option<InputFormat>) -> Result<Option<InputFormat>> {
+    if format.is_some() || source == "-" {
+        return Ok(format);
+    }
+
+    let source_path = Path::new(source);
+    if source_path.extension().is_none() {
+        return Ok(InputFormat::detect_from_extension(source_path));
+    }
+
+    InputFormat::detect_from_extension(source_path)
+        .map(Some)
+        .ok_or_else(|| {
+            TeraclioError::InvalidInput(format!(
+                "Unsupported input format for file '{}'. Supported formats: json, json5, yaml, toml, csv, tsv, xml, dotenv, ini, properties, ndjson, markdown. \
+                 Provide --format explicitly.",
+                source_path.display()
+            ))
+        })
+}
+
+fn display_source(source: &str) -> &str {
+    if source == "-" {
+        "stdin"
+    } else {
+        source
+    }
+}
+
+/**
+ * Open a data source file, or stdin for "-", as a buffered reader
+ * @author: skitsanos
+ */
+pub fn open_data_source(source: &str) -> Result<Box<dyn BufRead>> {
+    if source == "-" {
+        return Ok(Box::new(BufReader::new(std::io::stdin())));
+    }
+
+    let source_path = PathBuf::from(source);
+    if !source_path.exists() {
+        return Err(TeraclioError::InvalidInput(format!(
+            "Data source file does not exist: {}",
+            source_path.display()
+        )));
+    }
+
+    Ok(Box::new(BufReader::new(fs::File::open(&source_path)?)))
+}
+
+/**
+ * Parse already loaded text in the given format into a serde_json::Value
+ * @author: skitsanos
+ */
+pub fn parse_str(contents: &str, format: InputFormat, options: &ParseOptions) -> Result<Value> {
+    let value = match format {
+        InputFormat::Json => serde_json::from_str(contents).map_err(TeraclioError::JsonError)?,
+        InputFormat::Json5 => parse_json5(contents)?,
+        InputFormat::Yaml => parse_yaml(contents, options.yaml_documents)?,
+        InputFormat::Toml => parse_toml(contents)?,
+        InputFormat::Csv => parse_delimited(contents, b',', &options.csv)?,
+        InputFormat::Tsv => parse_delimited(contents, b'\t', &options.csv)?,
+        InputFormat::Xml => parse_xml(contents, &options.xml)?,
+        InputFormat::Dotenv => parse_dotenv(contents)?,
+        InputFormat::Ini => parse_ini(contents, options.expand_dotted_keys)?,
+        InputFormat::Properties => parse_properties(contents, options.expand_dotted_keys)?,
+        InputFormat::Ndjson => parse_ndjson(contents)?,
+        InputFormat::Markdown => parse_markdown(contents, &options.markdown)?,
     };
 
     Ok(value)
 }
 
+/**
+ * Temporary files and directories for unit tests
+ * @author: skitsanos
+ */
 #[cfg(test)]
-mod tests {
-    use super::{parse_data_source, InputFormat};
-    use crate::error::TeraclioError;
+pub(crate) mod test_support {
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::time::{SystemTime, UNIX_EPOCH};
 
-    fn unique_path(suffix: &str) -> std::path::PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    /**
+     * A path in the temp directory that no other test uses, ending in `.suffix` when given
+     * @author: skitsanos
+     */
+    pub(crate) fn unique_path(suffix: &str) -> PathBuf {
         let now = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .expect("time before unix epoch")
             .as_nanos();
+        let count = COUNTER.fetch_add(1, Ordering::Relaxed);
         let mut path = std::env::temp_dir();
         if suffix.is_empty() {
-            path.push(format!("teraclio-test-{now}"));
+            path.push(format!("teraclio-test-{now}-{count}"));
         } else {
-            path.push(format!("teraclio-test-{now}.{suffix}"));
+            path.push(format!("teraclio-test-{now}-{count}.{suffix}"));
         }
         path
     }
 
-    fn write_temp_file(name: &str, contents: &str) -> std::path::PathBuf {
+    pub(crate) fn write_temp_file(name: &str, contents: &str) -> PathBuf {
         let path = unique_path(name);
         std::fs::write(&path, contents).expect("write temp test file");
         path
     }
 
+    pub(crate) fn temp_dir() -> PathBuf {
+        let path = unique_path("");
+        std::fs::create_dir_all(&path).expect("create temp test dir");
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::test_support::write_temp_file;
+    use super::{parse_data_source, InputFormat, ParseOptions};
+    use crate::error::TeraclioError;
+
     #[test]
     fn parses_json_without_extension() {
         let path = write_temp_file("", r#"{"name":"value"}"#);
-        let result = parse_data_source(path.to_str().expect("utf8 path"), None).expect("parse");
+        let result = parse_data_source(path.to_str().expect("utf8 path"), &ParseOptions::default())
+            .expect("parse");
         assert_eq!(result["name"], "value");
         std::fs::remove_file(path).expect("cleanup");
     }
@@ -132,7 +366,8 @@ mod tests {
     #[test]
     fn rejects_unknown_extension_without_format() {
         let path = write_temp_file("cfg.txt", r#"{"name":"value"}"#);
-        let err = parse_data_source(path.to_str().expect("utf8 path"), None).expect_err("error");
+        let err = parse_data_source(path.to_str().expect("utf8 path"), &ParseOptions::default())
+            .expect_err("error");
         assert!(matches!(err, TeraclioError::InvalidInput(_)));
         if let TeraclioError::InvalidInput(msg) = err {
             assert!(msg.contains("Unsupported input format for file"), "{msg}");
@@ -143,9 +378,64 @@ mod tests {
     #[test]
     fn parses_unknown_extension_with_explicit_format() {
         let path = write_temp_file("cfg.txt", r#"name = "foo""#);
-        let result = parse_data_source(path.to_str().expect("utf8 path"), Some(InputFormat::Toml))
-            .expect("parse");
+        let options = ParseOptions {
+            format: Some(InputFormat::Toml),
+            ..ParseOptions::default()
+        };
+        let result = parse_data_source(path.to_str().expect("utf8 path"), &options).expect("parse");
         assert_eq!(result["name"], "foo");
         std::fs::remove_file(path).expect("cleanup");
     }
+
+    #[test]
+    fn detects_dotenv_file_names() {
+        use std::path::Path;
+        assert!(matches!(
+            InputFormat::detect_from_extension(Path::new("config/.env.local")),
+            Some(InputFormat::Dotenv)
+        ));
+        assert!(matches!(
+            InputFormat::detect_from_extension(Path::new(".env")),
+            Some(InputFormat::Dotenv)
+        ));
+        assert!(matches!(
+            InputFormat::detect_from_extension(Path::new("config/.env.json")),
+            Some(InputFormat::Json)
+        ));
+        assert!(matches!(
+            InputFormat::detect_from_extension(Path::new(".env.yaml")),
+            Some(InputFormat::Yaml)
+        ));
+    }
+
+    #[test]
+    fn detects_format_from_content() {
+        assert_eq!(
+            InputFormat::detect_from_content("{\"a\": 1}"),
+            Some(InputFormat::Json)
+        );
+        assert_eq!(
+            InputFormat::detect_from_content("{\"a\": 1}\n{\"a\": 2}\n"),
+            Some(InputFormat::Ndjson)
+        );
+        assert_eq!(
+            InputFormat::detect_from_content("[server]\nport = 80\n"),
+            Some(InputFormat::Toml)
+        );
+        assert_eq!(
+            InputFormat::detect_from_content("server:\n  port: 80\n"),
+            Some(InputFormat::Yaml)
+        );
+        assert_eq!(InputFormat::detect_from_content("just some text"), None);
+    }
+
+    #[test]
+    fn parses_tsv_by_extension() {
+        let path = write_temp_file("tsv", "name\tqty\npear\t2\n");
+        let result = parse_data_source(path.to_str().expect("utf8 path"), &ParseOptions::default())
+            .expect("parse");
+        assert_eq!(result[0]["name"], "pear");
+        assert_eq!(result[0]["qty"], "2");
+        std::fs::remove_file(path).expect("cleanup");
+    }
 }
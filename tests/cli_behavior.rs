@@ -109,3 +109,525 @@ fn check_mode_validates_output_format() {
 
     fs::remove_dir_all(temp_dir).expect("cleanup temp dir");
 }
+
+#[test]
+fn batch_mode_renders_one_file_per_record() {
+    let temp_dir = unique_temp_dir();
+    let data_path = temp_dir.join("events.jsonl");
+    let template_path = temp_dir.join("template.txt");
+    let dest_pattern = temp_dir.join("out").join("{{ data.id }}.txt");
+
+    write_file(
+        &data_path,
+        "{\"id\":\"first\",\"n\":1}\n\n{\"id\":\"second\",\"n\":2}\n",
+    );
+    write_file(&template_path, "n={{ data.n }}");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_teraclio"))
+        .args([
+            "-s",
+            data_path.to_str().expect("utf8 path"),
+            "-t",
+            template_path.to_str().expect("utf8 path"),
+            "-d",
+            dest_pattern.to_str().expect("utf8 path"),
+            "--batch",
+        ])
+        .output()
+        .expect("run teraclio");
+
+    assert!(output.status.success(), "expected command to succeed");
+    let first = fs::read_to_string(temp_dir.join("out").join("first.txt")).expect("first output");
+    let second =
+        fs::read_to_string(temp_dir.join("out").join("second.txt")).expect("second output");
+    assert_eq!(first, "n=1");
+    assert_eq!(second, "n=2");
+
+    fs::remove_dir_all(temp_dir).expect("cleanup temp dir");
+}
+
+#[test]
+fn yaml_source_with_docs_each_renders_one_file_per_document() {
+    let temp_dir = unique_temp_dir();
+    let data_path = temp_dir.join("items.yaml");
+    let template_path = temp_dir.join("template.txt");
+    let dest_pattern = temp_dir.join("out").join("{{ data.id }}.txt");
+
+    write_file(&data_path, "id: first\nn: 1\n---\nid: second\nn: 2\n---\n");
+    write_file(&template_path, "n={{ data.n }}");
+
+    let source = format!("{}?docs=each", data_path.to_str().expect("utf8 path"));
+    let output = Command::new(env!("CARGO_BIN_EXE_teraclio"))
+        .args([
+            "-s",
+            &source,
+            "-t",
+            template_path.to_str().expect("utf8 path"),
+            "-d",
+            dest_pattern.to_str().expect("utf8 path"),
+        ])
+        .output()
+        .expect("run teraclio");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let mut outputs: Vec<String> = fs::read_dir(temp_dir.join("out"))
+        .expect("output dir")
+        .map(|entry| {
+            entry
+                .expect("entry")
+                .file_name()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect();
+    outputs.sort();
+    assert_eq!(outputs, ["first.txt", "second.txt"]);
+    let second =
+        fs::read_to_string(temp_dir.join("out").join("second.txt")).expect("second output");
+    assert_eq!(second, "n=2");
+
+    fs::remove_dir_all(temp_dir).expect("cleanup temp dir");
+}
+
+#[test]
+fn glob_source_mounts_files_by_stem() {
+    let temp_dir = unique_temp_dir();
+    let services_dir = temp_dir.join("services");
+    let template_path = temp_dir.join("template.txt");
+
+    write_file(&services_dir.join("billing.yaml"), "port: 8080");
+    write_file(&services_dir.join("auth.json"), r#"{"port": 9090}"#);
+    write_file(
+        &template_path,
+        "{{ data.services.auth.port }},{{ data.services.billing.port }}",
+    );
+
+    let pattern = format!("services={}", services_dir.join("*").display());
+    let output = Command::new(env!("CARGO_BIN_EXE_teraclio"))
+        .args([
+            "-s",
+            pattern.as_str(),
+            "-t",
+            template_path.to_str().expect("utf8 path"),
+        ])
+        .output()
+        .expect("run teraclio");
+
+    assert!(output.status.success(), "expected command to succeed");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "9090,8080");
+
+    fs::remove_dir_all(temp_dir).expect("cleanup temp dir");
+}
+
+#[test]
+fn set_options_write_typed_nested_values() {
+    let temp_dir = unique_temp_dir();
+    let data_path = temp_dir.join("data.json");
+    let template_path = temp_dir.join("template.txt");
+
+    write_file(&data_path, r#"{"db": {"host": "localhost"}}"#);
+    write_file(&template_path, "{{ data | json_encode() }}");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_teraclio"))
+        .args([
+            "-s",
+            data_path.to_str().expect("utf8 path"),
+            "-t",
+            template_path.to_str().expect("utf8 path"),
+            "--set",
+            "db.port=5432",
+            "--set",
+            "servers[0].tls=true",
+            "--set-string",
+            "version=1.10",
+            "--set-json",
+            r#"limits={"cpu": 2}"#,
+        ])
+        .output()
+        .expect("run teraclio");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let rendered: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("rendered JSON");
+    assert_eq!(
+        rendered,
+        serde_json::json!({
+            "db": {"host": "localhost", "port": 5432},
+            "servers": [{"tls": true}],
+            "version": "1.10",
+            "limits": {"cpu": 2}
+        })
+    );
+
+    fs::remove_dir_all(temp_dir).expect("cleanup temp dir");
+}
+
+#[test]
+fn set_json_objects_deep_merge_with_the_sources() {
+    let temp_dir = unique_temp_dir();
+    let data_path = temp_dir.join("data.json");
+    let template_path = temp_dir.join("template.txt");
+
+    write_file(
+        &data_path,
+        r#"{"db": {"host": "h", "port": 1, "tags": ["a"]}}"#,
+    );
+    write_file(&template_path, "{{ data | json_encode() }}");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_teraclio"))
+        .args([
+            "-s",
+            data_path.to_str().expect("utf8 path"),
+            "-t",
+            template_path.to_str().expect("utf8 path"),
+            "--merge-path",
+            "db.tags=append",
+            "--set-json",
+            r#"db={"port": 2, "tags": ["b"]}"#,
+        ])
+        .output()
+        .expect("run teraclio");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let rendered: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("rendered JSON");
+    assert_eq!(
+        rendered,
+        serde_json::json!({"db": {"host": "h", "port": 2, "tags": ["a", "b"]}})
+    );
+
+    fs::remove_dir_all(temp_dir).expect("cleanup temp dir");
+}
+
+#[test]
+fn set_file_embeds_text_and_base64_encodes_binary() {
+    let temp_dir = unique_temp_dir();
+    let cert_path = temp_dir.join("cert.pem");
+    let blob_path = temp_dir.join("blob.bin");
+    let data_path = temp_dir.join("data.json");
+    let template_path = temp_dir.join("template.txt");
+
+    write_file(&data_path, "{}");
+    write_file(&cert_path, "-----BEGIN CERT-----\n");
+    fs::write(&blob_path, [0xff, 0x00, 0xfe]).expect("write binary file");
+    write_file(&template_path, "{{ data.tls.cert }}{{ data.blob }}");
+
+    let cert = format!("tls.cert={}", cert_path.display());
+    let blob = format!("blob={}", blob_path.display());
+    let output = Command::new(env!("CARGO_BIN_EXE_teraclio"))
+        .args([
+            "-s",
+            data_path.to_str().expect("utf8 path"),
+            "-t",
+            template_path.to_str().expect("utf8 path"),
+            "--set-file",
+            cert.as_str(),
+            "--set-file",
+            blob.as_str(),
+        ])
+        .output()
+        .expect("run teraclio");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "-----BEGIN CERT-----\n/wD+"
+    );
+
+    fs::remove_dir_all(temp_dir).expect("cleanup temp dir");
+}
+
+#[test]
+fn markdown_directory_becomes_sorted_document_list() {
+    let temp_dir = unique_temp_dir();
+    let posts_dir = temp_dir.join("posts");
+    let template_path = temp_dir.join("template.txt");
+
+    write_file(
+        &posts_dir.join("first.md"),
+        "---\ntitle: First\ndate: 2024-01-05\n---\nOne\n",
+    );
+    write_file(
+        &posts_dir.join("second.md"),
+        "+++\ntitle = \"Second\"\ndate = \"2024-03-01\"\n+++\nTwo\n",
+    );
+    write_file(
+        &template_path,
+        "{% for post in data.posts %}{{ post.slug }}:{{ post.title }}:{{ post.content | trim }};{% endfor %}",
+    );
+
+    let source = format!("posts={}?sort=-date", posts_dir.display());
+    let output = Command::new(env!("CARGO_BIN_EXE_teraclio"))
+        .args([
+            "-s",
+            source.as_str(),
+            "-t",
+            template_path.to_str().expect("utf8 path"),
+        ])
+        .output()
+        .expect("run teraclio");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "second:Second:Two;first:First:One;"
+    );
+
+    fs::remove_dir_all(temp_dir).expect("cleanup temp dir");
+}
+
+#[test]
+fn markdown_sort_orders_unquoted_toml_dates_with_yaml_dates() {
+    let temp_dir = unique_temp_dir();
+    let posts_dir = temp_dir.join("posts");
+    let template_path = temp_dir.join("template.txt");
+
+    write_file(
+        &posts_dir.join("january.md"),
+        "---\ndate: 2024-01-05\n---\nOne\n",
+    );
+    write_file(
+        &posts_dir.join("march.md"),
+        "+++\ndate = 2024-03-01\n+++\nTwo\n",
+    );
+    write_file(
+        &posts_dir.join("may.md"),
+        "---\ndate: 2024-05-01\n---\nThree\n",
+    );
+    write_file(
+        &template_path,
+        "{% for post in data %}{{ post.slug }}={{ post.date }};{% endfor %}",
+    );
+
+    let source = format!("{}?sort=-date", posts_dir.display());
+    let output = Command::new(env!("CARGO_BIN_EXE_teraclio"))
+        .args([
+            "-s",
+            source.as_str(),
+            "-t",
+            template_path.to_str().expect("utf8 path"),
+        ])
+        .output()
+        .expect("run teraclio");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "may=2024-05-01;march=2024-03-01;january=2024-01-05;"
+    );
+
+    fs::remove_dir_all(temp_dir).expect("cleanup temp dir");
+}
+
+#[test]
+fn explain_data_reports_origins_without_a_template() {
+    let temp_dir = unique_temp_dir();
+    let base_path = temp_dir.join("base.yaml");
+    let prod_path = temp_dir.join("prod.json");
+
+    write_file(&base_path, "db:\n  host: localhost\n  port: 5432\n");
+    write_file(
+        &prod_path,
+        "{\n  \"db\": {\n    \"host\": \"prod\"\n  }\n}\n",
+    );
+
+    let base = base_path.to_str().expect("utf8 path");
+    let prod = prod_path.to_str().expect("utf8 path");
+    let output = Command::new(env!("CARGO_BIN_EXE_teraclio"))
+        .args([
+            "--explain-data",
+            "-s",
+            base,
+            "-s",
+            prod,
+            "--set",
+            "db.port=6000",
+        ])
+        .output()
+        .expect("run teraclio");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&format!(
+            "db.host = \"prod\"\n    from {prod}:3\n    overrides \"localhost\" from {base}:2\n"
+        )),
+        "{stdout}"
+    );
+    assert!(
+        stdout.contains(&format!(
+            "db.port = 6000\n    from --set db.port=6000\n    overrides 5432 from {base}:3\n"
+        )),
+        "{stdout}"
+    );
+
+    fs::remove_dir_all(temp_dir).expect("cleanup temp dir");
+}
+
+#[test]
+fn dump_context_prints_wrapped_data_without_a_template() {
+    let temp_dir = unique_temp_dir();
+    let data_path = temp_dir.join("data.yaml");
+    write_file(&data_path, "name: app\nports: [80]\n");
+
+    let run = |format: &str| {
+        Command::new(env!("CARGO_BIN_EXE_teraclio"))
+            .args([
+                "-s",
+                data_path.to_str().expect("utf8 path"),
+                "--set",
+                "debug=true",
+                "--dump-context",
+                format,
+            ])
+            .output()
+            .expect("run teraclio")
+    };
+
+    let json = run("json");
+    assert!(
+        json.status.success(),
+        "{}",
+        String::from_utf8_lossy(&json.stderr)
+    );
+    let context: serde_json::Value = serde_json::from_slice(&json.stdout).expect("valid JSON");
+    assert_eq!(
+        context,
+        serde_json::json!({"data": {"name": "app", "ports": [80], "debug": true}})
+    );
+
+    let toml = run("toml");
+    assert_eq!(
+        String::from_utf8_lossy(&toml.stdout),
+        "[data]\ndebug = true\nname = \"app\"\nports = [80]\n"
+    );
+
+    fs::remove_dir_all(temp_dir).expect("cleanup temp dir");
+}
+
+#[test]
+fn inline_data_merges_in_command_line_order() {
+    use std::io::Write;
+    use std::process::Stdio;
+
+    let temp_dir = unique_temp_dir();
+    let base_path = temp_dir.join("base.yaml");
+    let template_path = temp_dir.join("template.txt");
+
+    write_file(&base_path, "name: base\nport: 80\nregion: eu\n");
+    write_file(
+        &template_path,
+        "{{ data.name }} {{ data.port }} {{ data.region }} {{ data.debug }}",
+    );
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_teraclio"))
+        .args([
+            "--data",
+            r#"{"name": "inline", "debug": false}"#,
+            "-s",
+            base_path.to_str().expect("utf8 path"),
+            "--data-yaml",
+            "port: 8080",
+            "-s",
+            "-",
+            "--data",
+            "debug = true",
+            "-t",
+            template_path.to_str().expect("utf8 path"),
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("run teraclio");
+    child
+        .stdin
+        .take()
+        .expect("stdin")
+        .write_all(br#"{"region": "us"}"#)
+        .expect("write stdin");
+    let output = child.wait_with_output().expect("wait for teraclio");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "base 8080 us true");
+
+    fs::remove_dir_all(temp_dir).expect("cleanup temp dir");
+}
+
+#[test]
+fn optional_and_lookup_sources_skip_missing_files() {
+    let temp_dir = unique_temp_dir();
+    let template_path = temp_dir.join("template.txt");
+
+    write_file(&temp_dir.join("config.yaml"), "level: base\nname: app\n");
+    write_file(&temp_dir.join("config/prod.yaml"), "level: prod\n");
+    write_file(&template_path, "{{ data.level }} {{ data.name }}");
+
+    let pattern = format!("{}/config/{{deploy.env}}/{{host}}.yaml", temp_dir.display());
+    let missing = temp_dir.join("local.yaml");
+    let output = Command::new(env!("CARGO_BIN_EXE_teraclio"))
+        .args([
+            "--source-lookup",
+            pattern.as_str(),
+            "-s",
+            &format!("?{}", missing.display()),
+            "--set",
+            "deploy.env=prod",
+            "--set",
+            "host=web1",
+            "-t",
+            template_path.to_str().expect("utf8 path"),
+        ])
+        .output()
+        .expect("run teraclio");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "prod app");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("config/prod/web1.yaml' not found, skipping"),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains("local.yaml' not found, skipping"),
+        "{stderr}"
+    );
+
+    fs::remove_dir_all(temp_dir).expect("cleanup temp dir");
+}